@@ -5,6 +5,7 @@ extern crate test;
 
 use test::Bencher;
 use pikkr::index_builder::IndexBuilder;
+use pikkr::simd::Backend;
 
 #[inline(never)]
 fn build_structural_indices(b: &mut Bencher, max_depth: usize, rec: &str) {
@@ -16,6 +17,21 @@ fn build_structural_indices(b: &mut Bencher, max_depth: usize, rec: &str) {
     });
 }
 
+#[inline(never)]
+fn build_structural_indices_with(b: &mut Bencher, backend: Backend) {
+    let rec = format!("[{}]", vec![r#"{"f0": "a", "f1": [1, 2], "f2": {"f1": "\"c\"", "f2": null}}"#; 100].join(","));
+    let mut index_builder = IndexBuilder::new(3);
+    if !Backend::supported().contains(&backend) {
+        return;
+    }
+    index_builder.set_backend(backend);
+    b.iter(|| {
+        index_builder
+            .build_structural_indices(rec.as_bytes())
+            .unwrap()
+    });
+}
+
 #[cfg(test)]
 mod index_builder {
     use super::*;
@@ -73,3 +89,25 @@ mod index_builder {
         build_structural_indices(b, depth, rec);
     }
 }
+
+#[cfg(test)]
+mod backends {
+    use super::*;
+
+    #[bench]
+    fn scalar(b: &mut Bencher) {
+        build_structural_indices_with(b, Backend::Scalar);
+    }
+
+    #[bench]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn sse2(b: &mut Bencher) {
+        build_structural_indices_with(b, Backend::Sse2);
+    }
+
+    #[bench]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn avx2(b: &mut Bencher) {
+        build_structural_indices_with(b, Backend::Avx2);
+    }
+}
@@ -22,7 +22,7 @@ fn basic_parse(b: &mut Bencher) {
         .unwrap();
 
     b.iter(|| {
-        let mut results = vec![None; query_strs.len()];
+        let mut results: Vec<Option<&[u8]>> = vec![None; query_strs.len()];
         parser
             .basic_parse(
                 json_rec,
@@ -30,7 +30,7 @@ fn basic_parse(b: &mut Bencher) {
                 0,
                 json_rec.len() - 1,
                 false,
-                &mut results,
+                &mut results[..],
             )
             .unwrap();
     });
@@ -50,7 +50,7 @@ fn speculative_parse(b: &mut Bencher) {
         .build_structural_indices(json_rec)
         .unwrap();
 
-    let mut results = vec![None; query_strs.len()];
+    let mut results: Vec<Option<&[u8]>> = vec![None; query_strs.len()];
     parser
         .basic_parse(
             json_rec,
@@ -58,19 +58,63 @@ fn speculative_parse(b: &mut Bencher) {
             0,
             json_rec.len() - 1,
             true,
-            &mut results,
+            &mut results[..],
         )
         .unwrap();
 
     b.iter(|| {
-        let mut results = vec![None; query_strs.len()];
+        let mut results: Vec<Option<&[u8]>> = vec![None; query_strs.len()];
         parser
             .speculative_parse(
                 json_rec,
                 &queries.as_node(),
                 0,
                 json_rec.len() - 1,
-                &mut results,
+                &mut results[..],
+            )
+            .unwrap();
+    });
+}
+
+#[bench]
+fn speculative_parse_ranked_candidates(b: &mut Bencher) {
+    let rare_rec_strs = [
+        r#"{ "aaa" : "AAA", "ddd" : { "d3": 333, "d1" : "D1" } }"#,
+        r#"{ "bbb" : 111, "aaa" : "AAA", "eee": { "e1": "EEE" }, "ddd" : { "d2" : "D2", "d3": 333, "d1" : "D1" } }"#,
+        r#"{ "ccc": ["C1", "C2"], "eee": { "e1": "EEE" }, "bbb" : 111, "ddd" : { "d1" : "D1", "d3": 333 }, "aaa" : "AAA" }"#,
+    ];
+    let json_rec_str = r#"{ "aaa" : "AAA", "bbb" : 111, "ccc": ["C1", "C2"], "ddd" : { "d1" : "D1", "d2" : "D2", "d3": 333 }, "eee": { "e1": "EEE" } } "#;
+    let json_rec = json_rec_str.as_bytes();
+    let query_strs = &["$.ddd.d1", "$.ddd.d3", "$.aaa", "$.bbb", "$.ccc", "$.eee"];
+
+    let queries = QueryTree::new(query_strs).unwrap();
+
+    // the rare layouts are learned first, and the common one afterwards
+    let mut parser = Parser::new(&queries);
+    let mut train = |rec: &[u8]| {
+        let mut results: Vec<Option<&[u8]>> = vec![None; query_strs.len()];
+        parser.parse(rec, &queries, true, &mut results[..]).unwrap();
+    };
+    for rec in rare_rec_strs.iter() {
+        train(rec.as_bytes());
+    }
+    for _ in 0..10 {
+        train(json_rec);
+    }
+    parser
+        .index_builder
+        .build_structural_indices(json_rec)
+        .unwrap();
+
+    b.iter(|| {
+        let mut results: Vec<Option<&[u8]>> = vec![None; query_strs.len()];
+        parser
+            .speculative_parse(
+                json_rec,
+                &queries.as_node(),
+                0,
+                json_rec.len() - 1,
+                &mut results[..],
             )
             .unwrap();
     });
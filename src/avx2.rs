@@ -0,0 +1,38 @@
+#[cfg(target_arch = "x86")]
+use std::arch::x86::{__m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8, _mm256_or_si256, _mm256_set1_epi8};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{__m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8, _mm256_or_si256, _mm256_set1_epi8};
+use super::simd;
+
+/// 64 bytes in two AVX registers
+pub struct Block(__m256i, __m256i);
+
+impl simd::Block for Block {
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn load(p: *const u8) -> Self {
+        Block(
+            _mm256_loadu_si256(p as *const __m256i),
+            _mm256_loadu_si256(p.add(32) as *const __m256i),
+        )
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn eq(&self, c: u8) -> u64 {
+        let m = _mm256_set1_epi8(c as i8);
+        let i1 = _mm256_movemask_epi8(_mm256_cmpeq_epi8(self.0, m));
+        let i2 = _mm256_movemask_epi8(_mm256_cmpeq_epi8(self.1, m));
+        u64::from(i1 as u32) | (u64::from(i2 as u32) << 32)
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn eq_any(&self, c1: u8, c2: u8) -> u64 {
+        let m1 = _mm256_set1_epi8(c1 as i8);
+        let m2 = _mm256_set1_epi8(c2 as i8);
+        let i1 = _mm256_movemask_epi8(_mm256_or_si256(_mm256_cmpeq_epi8(self.0, m1), _mm256_cmpeq_epi8(self.0, m2)));
+        let i2 = _mm256_movemask_epi8(_mm256_or_si256(_mm256_cmpeq_epi8(self.1, m1), _mm256_cmpeq_epi8(self.1, m2)));
+        u64::from(i1 as u32) | (u64::from(i2 as u32) << 32)
+    }
+}
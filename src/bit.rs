@@ -8,9 +8,17 @@ pub fn e(x: u64) -> u64 {
     x & x.wrapping_neg()
 }
 
+/// Returns the prefix XOR of `x`, whose `i`-th bit is the parity of the bits of `x` up to the `i`-th one.
 #[inline]
-pub fn s(x: u64) -> u64 {
-    x ^ x.saturating_sub(1)
+pub fn prefix_xor(x: u64) -> u64 {
+    let mut x = x;
+    x ^= x << 1;
+    x ^= x << 2;
+    x ^= x << 4;
+    x ^= x << 8;
+    x ^= x << 16;
+    x ^= x << 32;
+    x
 }
 
 #[cfg(test)]
@@ -168,7 +176,7 @@ mod tests {
     }
 
     #[test]
-    fn test_s() {
+    fn test_prefix_xor() {
         struct TestCase {
             x: u64,
             want: u64,
@@ -181,64 +189,30 @@ mod tests {
             },
             TestCase {
                 x: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000001u64,
-                want: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000001u64,
-            },
-            TestCase {
-                x: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000010u64,
-                want: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000011u64,
-            },
-            TestCase {
-                x: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000001_00000000u64,
-                want: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000001_11111111u64,
-            },
-            TestCase {
-                x: 0b00000000_00000000_00000000_00000000_00000000_00000001_00000000_00000000u64,
-                want: 0b00000000_00000000_00000000_00000000_00000000_00000001_11111111_11111111u64,
+                want: 0b11111111_11111111_11111111_11111111_11111111_11111111_11111111_11111111u64,
             },
             TestCase {
-                x: 0b00000000_00000000_00000000_00000000_00000001_00000000_00000000_00000000u64,
-                want: 0b00000000_00000000_00000000_00000000_00000001_11111111_11111111_11111111u64,
-            },
-            TestCase {
-                x: 0b00000000_00000000_00000000_00000001_00000000_00000000_00000000_00000000u64,
-                want: 0b00000000_00000000_00000000_00000001_11111111_11111111_11111111_11111111u64,
+                x: 0b00000000_00000000_00000000_00000000_00000000_00000000_01000000_00000100u64,
+                want: 0b00000000_00000000_00000000_00000000_00000000_00000000_00111111_11111100u64,
             },
             TestCase {
-                x: 0b00000000_00000000_00000001_00000000_00000000_00000000_00000000_00000000u64,
-                want: 0b00000000_00000000_00000001_11111111_11111111_11111111_11111111_11111111u64,
-            },
-            TestCase {
-                x: 0b00000000_00000001_00000000_00000000_00000000_00000000_00000000_00000000u64,
-                want: 0b00000000_00000001_11111111_11111111_11111111_11111111_11111111_11111111u64,
-            },
-            TestCase {
-                x: 0b00000001_00000000_00000000_00000000_00000000_00000000_00000000_00000000u64,
-                want: 0b00000001_11111111_11111111_11111111_11111111_11111111_11111111_11111111u64,
-            },
-            TestCase {
-                x: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000011u64,
-                want: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000001u64,
-            },
-            TestCase {
-                x: 0b10000000_00100000_00000000_00000000_00000000_00000000_00000100_00000000u64,
-                want: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000111_11111111u64,
-            },
-            TestCase {
-                x: 0b00000000_00010000_00110010_00000000_00000000_00001100_00000000_00000000u64,
-                want: 0b00000000_00000000_00000000_00000000_00000000_00000111_11111111_11111111u64,
-            },
-            TestCase {
-                x: 0b11100000_00000000_00000000_00000000_00000000_00000000_00000000_00000000u64,
-                want: 0b00111111_11111111_11111111_11111111_11111111_11111111_11111111_11111111u64,
+                x: 0b10000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000u64,
+                want: 0b10000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000u64,
             },
             TestCase {
                 x: 0b11111111_11111111_11111111_11111111_11111111_11111111_11111111_11111111u64,
-                want: 0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000001u64,
+                want: 0b01010101_01010101_01010101_01010101_01010101_01010101_01010101_01010101u64,
             },
         ];
 
         for test_case in test_cases {
-            assert_eq!(test_case.want, s(test_case.x));
+            assert_eq!(test_case.want, prefix_xor(test_case.x));
+            #[cfg(target_arch = "x86_64")]
+            {
+                if is_x86_feature_detected!("pclmulqdq") {
+                    assert_eq!(test_case.want, unsafe { super::super::clmul::prefix_xor(test_case.x) });
+                }
+            }
         }
     }
 }
@@ -0,0 +1,22 @@
+use std::arch::x86_64::{_mm_clmulepi64_si128, _mm_cvtsi128_si64, _mm_set1_epi8, _mm_set_epi64x};
+
+/// Returns the prefix XOR of `x`, whose `i`-th bit is the parity of the bits of `x` up to the `i`-th one.
+///
+/// The carry-less product with all ones XORs each bit of `x` into all the higher bits at once.
+#[inline]
+#[target_feature(enable = "pclmulqdq")]
+pub unsafe fn prefix_xor(x: u64) -> u64 {
+    let p = _mm_clmulepi64_si128(_mm_set_epi64x(0, x as i64), _mm_set1_epi8(-1), 0);
+    _mm_cvtsi128_si64(p) as u64
+}
+
+/// Builds the string mask bitmap in the same way as `index_builder::build_string_mask_bitmap` with PCLMULQDQ instructions.
+#[target_feature(enable = "pclmulqdq")]
+pub unsafe fn build_string_mask_bitmap(b_quote: &[u64], b_string_mask: &mut Vec<u64>) {
+    let mut inside = 0u64;
+    for &m_quote in b_quote {
+        let m_string = prefix_xor(m_quote) ^ inside;
+        b_string_mask.push(!(m_string ^ m_quote));
+        inside = ((m_string as i64) >> 63) as u64;
+    }
+}
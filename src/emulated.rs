@@ -3,9 +3,31 @@
 //! This is likely not as fast as using actual AVX, but will work without intrinsics.
 //! It also makes for a neat benchmark.
 
+use std::slice;
+use super::simd;
+
 #[allow(non_camel_case_types)]
 pub type m256i = [u64; 4];
 
+/// 64 bytes in two emulated AVX registers
+pub struct Block(m256i, m256i);
+
+impl simd::Block for Block {
+    #[inline(always)]
+    unsafe fn load(p: *const u8) -> Self {
+        let s = slice::from_raw_parts(p, 64);
+        Block(avx::u8_to_m256i(s, 0), avx::u8_to_m256i(s, 32))
+    }
+
+    #[inline(always)]
+    unsafe fn eq(&self, c: u8) -> u64 {
+        let m = avx::mm256i(c as i8);
+        let i1 = mm256_movemask_epi8(mm256_cmpeq_epi8(self.0, m));
+        let i2 = mm256_movemask_epi8(mm256_cmpeq_epi8(self.1, m));
+        u64::from(i1) | (u64::from(i2) << 32)
+    }
+}
+
 #[allow(unused_unsafe)]
 pub mod avx {
     use super::m256i;
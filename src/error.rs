@@ -1,10 +1,13 @@
 use std::error;
 use std::fmt;
+use std::io;
 
 /// Th error for parsing a JSON record.
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct Error {
     kind: ErrorKind,
+    line: Option<usize>,
+    io_error: Option<io::Error>,
 }
 
 impl Error {
@@ -12,18 +15,59 @@ impl Error {
     pub fn kind(&self) -> ErrorKind {
         self.kind
     }
+
+    /// Returns the line number of the record which caused this error, if it was read from a stream.
+    #[inline]
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Returns the underlying I/O error, if the kind of this error is `ErrorKind::Io`.
+    #[inline]
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.io_error.as_ref()
+    }
+
+    #[inline]
+    pub(crate) fn at_line(mut self, line: usize) -> Error {
+        self.line = Some(line);
+        self
+    }
 }
 
 impl From<ErrorKind> for Error {
     #[inline]
     fn from(kind: ErrorKind) -> Error {
-        Error { kind: kind }
+        Error { kind: kind, line: None, io_error: None }
+    }
+}
+
+impl From<io::Error> for Error {
+    #[inline]
+    fn from(err: io::Error) -> Error {
+        Error { kind: ErrorKind::Io, line: None, io_error: Some(err) }
+    }
+}
+
+/// The errors are equal if they have the same kinds, line numbers and kinds of I/O errors.
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        self.kind == other.kind
+            && self.line == other.line
+            && self.io_error.as_ref().map(|e| e.kind()) == other.io_error.as_ref().map(|e| e.kind())
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{}", self.kind.as_str())
+        write!(fmt, "{}", self.kind.as_str())?;
+        if let Some(ref err) = self.io_error {
+            write!(fmt, ": {}", err)?;
+        }
+        if let Some(line) = self.line {
+            write!(fmt, " at line {}", line)?;
+        }
+        Ok(())
     }
 }
 
@@ -32,8 +76,12 @@ impl error::Error for Error {
         self.kind.as_str()
     }
 
-    fn cause(&self) -> Option<&error::Error> {
-        None
+    fn cause(&self) -> Option<&dyn error::Error> {
+        self.io_error.as_ref().map(|e| e as &dyn error::Error)
+    }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.io_error.as_ref().map(|e| e as &(dyn error::Error + 'static))
     }
 }
 
@@ -42,6 +90,10 @@ impl error::Error for Error {
 pub enum ErrorKind {
     InvalidQuery,
     InvalidRecord,
+    TypeMismatch,
+    Io,
+    InvalidStats,
+    TooDeep,
 }
 
 impl ErrorKind {
@@ -50,6 +102,10 @@ impl ErrorKind {
         match *self {
             ErrorKind::InvalidQuery => "invalid query",
             ErrorKind::InvalidRecord => "invalid record",
+            ErrorKind::TypeMismatch => "type mismatch",
+            ErrorKind::Io => "I/O error",
+            ErrorKind::InvalidStats => "invalid stats",
+            ErrorKind::TooDeep => "too deeply nested record",
         }
     }
 }
@@ -1,24 +1,24 @@
-use super::avx;
 use super::bit;
-use super::error::ErrorKind;
+#[cfg(target_arch = "x86_64")]
+use super::clmul;
+use super::error::{Error, ErrorKind};
 use super::result::Result;
-use super::utf8::{BACKSLASH, COLON, LEFT_BRACE, QUOTE, RIGHT_BRACE};
-#[cfg(feature = "avx-accel")]
-use x86intrin::{m256i, mm256_cmpeq_epi8, mm256_movemask_epi8};
-#[cfg(not(feature = "avx-accel"))]
-use emulated::{m256i, mm256_cmpeq_epi8, mm256_movemask_epi8};
+use super::simd::Backend;
+use super::utf8::{CR, HT, LF, SPACE};
+use std::ops::Range;
 
-#[derive(Debug)]
+/// The default limit of the nesting depth of the levels indexed for a record
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+#[derive(Clone, Debug)]
 pub struct IndexBuilder {
-    backslash: m256i,
-    quote: m256i,
-    colon: m256i,
-    left_brace: m256i,
-    right_brace: m256i,
+    backend: Backend,
+    clmul: bool,
 
     b_backslash: Vec<u64>,
     pub(crate) b_quote: Vec<u64>,
     b_colon: Vec<u64>,
+    b_comma: Vec<u64>,
     b_left: Vec<u64>,
     b_right: Vec<u64>,
     b_string_mask: Vec<u64>,
@@ -26,23 +26,26 @@ pub struct IndexBuilder {
     s_left: Vec<(usize, u64)>,
 
     pub(crate) index: Vec<Vec<u64>>,
+    pub(crate) comma_index: Vec<Vec<u64>>,
     depth: usize,
+    max_depth: usize,
 }
 
 impl IndexBuilder {
     pub fn new(depth: usize) -> Self {
         let index = vec![Vec::new(); depth];
+        let comma_index = vec![Vec::new(); depth];
+
+        let backend = Backend::detect();
 
         Self {
-            backslash: avx::mm256i(BACKSLASH as i8),
-            quote: avx::mm256i(QUOTE as i8),
-            colon: avx::mm256i(COLON as i8),
-            left_brace: avx::mm256i(LEFT_BRACE as i8),
-            right_brace: avx::mm256i(RIGHT_BRACE as i8),
+            backend,
+            clmul: uses_clmul(backend),
 
             b_backslash: Vec::new(),
             b_quote: Vec::new(),
             b_colon: Vec::new(),
+            b_comma: Vec::new(),
             b_left: Vec::new(),
             b_right: Vec::new(),
             b_string_mask: Vec::new(),
@@ -50,204 +53,259 @@ impl IndexBuilder {
             s_left: Vec::new(),
 
             index,
+            comma_index,
             depth,
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 
+    /// Creates an index builder whose levels grow with the depth of each record.
+    pub fn unbounded() -> Self {
+        let mut index_builder = Self::new(0);
+        index_builder.depth = usize::MAX;
+        index_builder
+    }
+
+    /// Returns the limit of the nesting depth of the levels indexed for a record.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sets the limit of the nesting depth of the levels indexed for a record, which is `DEFAULT_MAX_DEPTH` by default.
+    ///
+    /// Building the index of a record fails if it has an object or array nested deeper than this limit within the levels to be indexed,
+    /// which grow with the depth of each record for an unbounded index builder.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Returns the implementation which builds the character bitmaps, which is the fastest one supported by the CPU by default.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Replaces the implementation which builds the character bitmaps.
+    ///
+    /// The carry-less multiplication is not used for the string mask either if `backend` is `Backend::Scalar`.
+    ///
+    /// # Panics
+    /// This function will panic if `backend` is not supported by the CPU.
+    pub fn set_backend(&mut self, backend: Backend) {
+        assert!(Backend::supported().contains(&backend), "{:?} is not supported by the CPU", backend);
+        self.backend = backend;
+        self.clmul = uses_clmul(backend);
+    }
+
     #[inline(always)]
     pub fn build_structural_indices(&mut self, rec: &[u8]) -> Result<()> {
         let b_len = (rec.len() + 63) / 64;
 
+        if b_len > self.b_backslash.capacity() {
+            for b in self.index.iter_mut() {
+                b.reserve_exact(b_len);
+            }
+            for b in self.comma_index.iter_mut() {
+                b.reserve_exact(b_len);
+            }
+        }
+
+        self.build_structural_bitmaps(rec);
+
+        build_leveled_bitmap(
+            &self.b_colon,
+            &self.b_comma,
+            &self.b_left,
+            &self.b_right,
+            self.depth,
+            self.max_depth,
+            &mut self.s_left,
+            &mut self.index,
+            &mut self.comma_index,
+        )
+    }
+
+    /// Finds the objects and arrays at the top level of `s`, which are concatenated with optional whitespaces in between,
+    /// and appends their ranges to `records`.
+    ///
+    /// This function will return an error if `s` has unbalanced braces or brackets, or a value outside of objects and arrays.
+    pub fn split_records(&mut self, s: &[u8], records: &mut Vec<Range<usize>>) -> Result<()> {
+        self.build_structural_bitmaps(s);
+
+        let mut depth = 0usize;
+        let mut start = 0;
+        let mut end = 0;
+        for i in 0..self.b_left.len() {
+            let mut m_left = self.b_left[i];
+            let mut m_right = self.b_right[i];
+            while m_left | m_right != 0 {
+                let m_bit = bit::e(m_left | m_right);
+                let offset = i * 64 + (m_bit.trailing_zeros() as usize);
+                if m_left & m_bit != 0 {
+                    if depth == 0 {
+                        if !is_whitespace(&s[end..offset]) {
+                            return Err(Error::from(ErrorKind::InvalidRecord));
+                        }
+                        start = offset;
+                    }
+                    depth += 1;
+                    m_left = bit::r(m_left);
+                } else {
+                    depth = depth.checked_sub(1).ok_or_else(|| Error::from(ErrorKind::InvalidRecord))?;
+                    if depth == 0 {
+                        end = offset + 1;
+                        records.push(start..end);
+                    }
+                    m_right = bit::r(m_right);
+                }
+            }
+        }
+        if depth != 0 || !is_whitespace(&s[end..]) {
+            return Err(Error::from(ErrorKind::InvalidRecord));
+        }
+        Ok(())
+    }
+
+    /// Builds the bitmaps of the structural characters outside of strings.
+    #[inline(always)]
+    fn build_structural_bitmaps(&mut self, rec: &[u8]) {
+        let b_len = (rec.len() + 63) / 64;
+
         self.b_backslash.clear();
         self.b_quote.clear();
         self.b_colon.clear();
+        self.b_comma.clear();
         self.b_left.clear();
         self.b_right.clear();
         self.b_string_mask.clear();
-        for b in &mut self.index {
-            b.clear();
-        }
 
         if b_len > self.b_backslash.capacity() {
             self.b_backslash.reserve_exact(b_len);
             self.b_quote.reserve_exact(b_len);
             self.b_colon.reserve_exact(b_len);
+            self.b_comma.reserve_exact(b_len);
             self.b_left.reserve_exact(b_len);
             self.b_right.reserve_exact(b_len);
             self.b_string_mask.reserve_exact(b_len);
-            for b in self.index.iter_mut() {
-                b.reserve_exact(b_len);
-            }
         }
 
-        build_structural_character_bitmap(
+        self.backend.build_structural_character_bitmap(
             rec,
             &mut self.b_backslash,
             &mut self.b_quote,
             &mut self.b_colon,
+            &mut self.b_comma,
             &mut self.b_left,
             &mut self.b_right,
-            &self.backslash,
-            &self.quote,
-            &self.colon,
-            &self.left_brace,
-            &self.right_brace,
         );
 
         build_structural_quote_bitmap(&self.b_backslash, &mut self.b_quote);
 
-        build_string_mask_bitmap(&self.b_quote, &mut self.b_string_mask);
+        if self.clmul {
+            #[cfg(target_arch = "x86_64")]
+            unsafe { clmul::build_string_mask_bitmap(&self.b_quote, &mut self.b_string_mask) };
+        } else {
+            build_string_mask_bitmap(&self.b_quote, &mut self.b_string_mask);
+        }
 
         for (i, b) in self.b_string_mask.iter().enumerate() {
             self.b_colon[i] &= *b;
+            self.b_comma[i] &= *b;
             self.b_left[i] &= *b;
             self.b_right[i] &= *b;
         }
+    }
 
-        build_leveled_colon_bitmap(
-            &self.b_colon,
-            &self.b_left,
-            &self.b_right,
-            self.depth,
-            &mut self.s_left,
-            &mut self.index,
-        )
+    /// Returns whether `rec[start..end]` of the last indexed record contains a backslash.
+    #[inline]
+    pub(crate) fn contains_backslash(&self, start: usize, end: usize) -> bool {
+        if start >= end {
+            return false;
+        }
+        let (si, ei) = (start / 64, (end - 1) / 64);
+        for i in si..ei + 1 {
+            let mut m = self.b_backslash[i];
+            if i == si {
+                m &= !0u64 << (start % 64);
+            }
+            if i == ei {
+                m &= !0u64 >> (63 - (end - 1) % 64);
+            }
+            if m != 0 {
+                return true;
+            }
+        }
+        false
     }
 }
 
 
 #[inline]
-pub fn build_structural_character_bitmap(s: &[u8], b_backslash: &mut Vec<u64>, b_quote: &mut Vec<u64>, b_colon: &mut Vec<u64>, b_left: &mut Vec<u64>, b_right: &mut Vec<u64>, m_backslash: &m256i, m_quote: &m256i, m_colon: &m256i, m_left: &m256i, m_right: &m256i) {
-    let n = s.len();
-    let mut i = 0;
-    while i + 63 < n {
-        let s1 = unsafe { avx::u8_to_m256i(s, i) };
-        let s2 = unsafe { avx::u8_to_m256i(s, i + 32) };
-
-        b_backslash.push(mbitmap(&s1, &s2, m_backslash));
-        b_quote.push(mbitmap(&s1, &s2, m_quote));
-        b_colon.push(mbitmap(&s1, &s2, m_colon));
-        b_left.push(mbitmap(&s1, &s2, m_left));
-        b_right.push(mbitmap(&s1, &s2, m_right));
-
-        i += 64;
-    }
-
-    if i + 32 < n {
-        let s1 = unsafe { avx::u8_to_m256i(s, i) };
-        let s2 = unsafe { avx::u8_to_m256i_rest(s, i + 32) };
-
-        b_backslash.push(mbitmap(&s1, &s2, m_backslash));
-        b_quote.push(mbitmap(&s1, &s2, m_quote));
-        b_colon.push(mbitmap(&s1, &s2, m_colon));
-        b_left.push(mbitmap(&s1, &s2, m_left));
-        b_right.push(mbitmap(&s1, &s2, m_right));
-    } else if i + 32 == n {
-        let s1 = unsafe { avx::u8_to_m256i(s, i) };
-
-        b_backslash.push(mbitmap_partial(&s1, m_backslash));
-        b_quote.push(mbitmap_partial(&s1, m_quote));
-        b_colon.push(mbitmap_partial(&s1, m_colon));
-        b_left.push(mbitmap_partial(&s1, m_left));
-        b_right.push(mbitmap_partial(&s1, m_right));
-    } else if i < n {
-        let s1 = unsafe { avx::u8_to_m256i_rest(s, i) };
-
-        b_backslash.push(mbitmap_partial(&s1, m_backslash));
-        b_quote.push(mbitmap_partial(&s1, m_quote));
-        b_colon.push(mbitmap_partial(&s1, m_colon));
-        b_left.push(mbitmap_partial(&s1, m_left));
-        b_right.push(mbitmap_partial(&s1, m_right));
-    }
+fn is_whitespace(s: &[u8]) -> bool {
+    s.iter().all(|&c| c == SPACE || c == HT || c == LF || c == CR)
 }
 
+/// Clears the bits of the quotes escaped by an odd number of consecutive backslashes.
+///
+/// The runs of backslashes are found by adding their first bits to them, which carries across each run.
+/// A run ends with an escaping backslash if its length is odd, i.e. its start and end have different parities.
 #[inline]
-fn mbitmap(s1: &m256i, s2: &m256i, m: &m256i) -> u64 {
-    let i1 = mm256_movemask_epi8(mm256_cmpeq_epi8(*s1, *m));
-    let i2 = mm256_movemask_epi8(mm256_cmpeq_epi8(*s2, *m));
-    u64::from(i1 as u32) | (u64::from(i2 as u32) << 32)
+pub fn build_structural_quote_bitmap(b_backslash: &[u64], b_quote: &mut Vec<u64>) {
+    const EVEN_BITS: u64 = 0x5555_5555_5555_5555;
+    const ODD_BITS: u64 = !EVEN_BITS;
+    // whether the previous word ends with an odd-length run of backslashes
+    let mut prev_odd = 0u64;
+    for (m_quote, &m_backslash) in b_quote.iter_mut().zip(b_backslash) {
+        let m_start = m_backslash & !(m_backslash << 1);
+        // a run continued from the previous word is counted from its start there
+        let m_even_start_mask = EVEN_BITS ^ prev_odd;
+        let m_even_start = m_start & m_even_start_mask;
+        let m_odd_start = m_start & !m_even_start_mask;
+        let m_even_carry = m_backslash.wrapping_add(m_even_start);
+        let (m_odd_carry, overflow) = m_backslash.overflowing_add(m_odd_start);
+        let m_odd_carry = m_odd_carry | prev_odd;
+        prev_odd = overflow as u64;
+        let m_even_start_odd_end = m_even_carry & !m_backslash & ODD_BITS;
+        let m_odd_start_even_end = m_odd_carry & !m_backslash & EVEN_BITS;
+        *m_quote &= !(m_even_start_odd_end | m_odd_start_even_end);
+    }
 }
 
+/// Returns whether the string mask is built with the carry-less multiplication along with `backend`, which is detected only once by the caller.
+#[cfg(target_arch = "x86_64")]
 #[inline]
-fn mbitmap_partial(s: &m256i, m: &m256i) -> u64 {
-    u64::from(mm256_movemask_epi8(mm256_cmpeq_epi8(*s, *m)) as u32)
+fn uses_clmul(backend: Backend) -> bool {
+    backend != Backend::Scalar && is_x86_feature_detected!("pclmulqdq")
 }
 
-
+#[cfg(not(target_arch = "x86_64"))]
 #[inline]
-pub fn build_structural_quote_bitmap(b_backslash: &[u64], b_quote: &mut Vec<u64>) {
-    let n = b_quote.len();
-    if n < 1 {
-        return;
-    }
-    let mut b_unstructural_quote = Vec::with_capacity(n);
-    let mut b_backslash_quote = Vec::with_capacity(n);
-    for i in 0..n - 1 {
-        b_backslash_quote.push(((b_quote[i] >> 1) | b_quote[i + 1] << 63) & b_backslash[i]);
-    }
-    b_backslash_quote.push((b_quote[n - 1] >> 1) & b_backslash[n - 1]);
-    for (i, backslash_quote) in b_backslash_quote.iter().enumerate() {
-        let mut unstructural_quote = 0u64;
-        let mut backslash_quote = *backslash_quote;
-        while backslash_quote != 0 {
-            let backslash_quote_mask = bit::s(backslash_quote);
-            let backslash_quote_mask_ones_num = backslash_quote_mask.count_ones();
-            let mut consecutive_backslash_num = 0;
-            for j in (0..i + 1).rev() {
-                let backslash_b = b_backslash[j];
-                if j == i {
-                    let backslash_b_mask = (backslash_b & backslash_quote_mask) << (64 - backslash_quote_mask_ones_num);
-                    let leading_ones_num = (!backslash_b_mask).leading_zeros();
-                    consecutive_backslash_num += leading_ones_num;
-                    if leading_ones_num != backslash_quote_mask_ones_num {
-                        break;
-                    }
-                } else {
-                    let backslash_b_mask = backslash_b & 0xffff_ffff_ffff_ffffu64;
-                    let leading_ones_num = (!backslash_b_mask).leading_zeros();
-                    consecutive_backslash_num += leading_ones_num;
-                    if leading_ones_num != 64 {
-                        break;
-                    }
-                }
-            }
-            if consecutive_backslash_num & 1 == 1 {
-                unstructural_quote |= bit::e(backslash_quote);
-            }
-            backslash_quote = bit::r(backslash_quote);
-        }
-        b_unstructural_quote.push(!unstructural_quote);
-    }
-    b_quote[0] &= b_unstructural_quote[0] << 1;
-    for i in 1..n {
-        b_quote[i] &= (b_unstructural_quote[i] << 1) | (b_unstructural_quote[i - 1] >> 63);
-    }
+fn uses_clmul(_backend: Backend) -> bool {
+    false
 }
 
+/// Builds the bitmap whose bits are set outside of strings, where the opening quotes are outside and the closing ones are inside.
 #[inline]
 pub fn build_string_mask_bitmap(b_quote: &[u64], b_string_mask: &mut Vec<u64>) {
-    let mut n = 0;
-    for m_quote in b_quote {
-        let mut m_quote = *m_quote;
-        let mut m_string = 0u64;
-        while m_quote != 0 {
-            let m = bit::s(m_quote);
-            m_string ^= m;
-            m_quote = bit::r(m_quote);
-            n += 1;
-        }
-        if n & 1 == 0 {
-            m_string = !m_string;
-        }
-        b_string_mask.push(m_string);
+    // all ones while a string continues from the previous word
+    let mut inside = 0u64;
+    for &m_quote in b_quote {
+        let m_string = bit::prefix_xor(m_quote) ^ inside;
+        b_string_mask.push(!(m_string ^ m_quote));
+        inside = ((m_string as i64) >> 63) as u64;
     }
 }
 
+/// Builds the leveled colon and comma bitmaps.
+///
+/// `colon_index[l]` and `comma_index[l]` keep only the colons and commas whose level is at most `l`,
+/// so the positions at level `l` are obtained by restricting them to the span of a value at level `l`.
+/// The indices have `l` levels, or as many levels as the depth of the record if `l` is `usize::MAX`.
+/// This function will return an error if the record is nested deeper than `max_depth` within the levels.
 #[inline]
-pub fn build_leveled_colon_bitmap(b_colon: &[u64], b_left: &[u64], b_right: &[u64], l: usize, s_left: &mut Vec<(usize, u64)>, index: &mut Vec<Vec<u64>>) -> Result<()> {
-    for b in index.iter_mut() {
-        b.extend(b_colon);
-    }
+#[allow(clippy::too_many_arguments)]
+pub fn build_leveled_bitmap(b_colon: &[u64], b_comma: &[u64], b_left: &[u64], b_right: &[u64], l: usize, max_depth: usize, s_left: &mut Vec<(usize, u64)>, colon_index: &mut Vec<Vec<u64>>, comma_index: &mut Vec<Vec<u64>>) -> Result<()> {
+    // the number of levels filled so far, which are as many as the depth of the record
+    let mut levels = 0;
     s_left.clear();
     for i in 0..b_right.len() {
         let mut m_left = b_left[i];
@@ -257,6 +315,21 @@ pub fn build_leveled_colon_bitmap(b_colon: &[u64], b_left: &[u64], b_right: &[u6
             let mut m_leftbit = bit::e(m_left);
             while m_leftbit != 0 && (m_rightbit == 0 || m_leftbit < m_rightbit) {
                 s_left.push((i, m_leftbit));
+                if s_left.len() > levels && s_left.len() <= l {
+                    if s_left.len() > max_depth {
+                        return Err(Error::from(ErrorKind::TooDeep));
+                    }
+                    if levels < colon_index.len() {
+                        colon_index[levels].clear();
+                        colon_index[levels].extend(b_colon);
+                        comma_index[levels].clear();
+                        comma_index[levels].extend(b_comma);
+                    } else {
+                        colon_index.push(b_colon.to_vec());
+                        comma_index.push(b_comma.to_vec());
+                    }
+                    levels += 1;
+                }
                 m_left = bit::r(m_left);
                 m_leftbit = bit::e(m_left);
             }
@@ -266,15 +339,8 @@ pub fn build_leveled_colon_bitmap(b_colon: &[u64], b_left: &[u64], b_right: &[u6
                 if s_left.len() > 0 {
                     let upper_l = s_left.len() - 1;
                     if upper_l < l {
-                        if i == j {
-                            index[upper_l][i] &= !(m_rightbit.wrapping_sub(m_leftbit));
-                        } else {
-                            index[upper_l][j] &= m_leftbit.wrapping_sub(1);
-                            index[upper_l][i] &= !(m_rightbit.wrapping_sub(1));
-                            for k in j + 1..i {
-                                index[upper_l][k] = 0
-                            }
-                        }
+                        clear_leveled_bitmap(&mut colon_index[upper_l], i, m_rightbit, j, m_leftbit);
+                        clear_leveled_bitmap(&mut comma_index[upper_l], i, m_rightbit, j, m_leftbit);
                     }
                 }
             }
@@ -284,19 +350,173 @@ pub fn build_leveled_colon_bitmap(b_colon: &[u64], b_left: &[u64], b_right: &[u6
             }
         }
     }
+    if l == usize::MAX {
+        // the levels of an unbounded index do not outlive the record
+        colon_index.truncate(levels);
+        comma_index.truncate(levels);
+    } else {
+        colon_index.resize(l, Vec::new());
+        comma_index.resize(l, Vec::new());
+        for level in levels..l {
+            colon_index[level].clear();
+            colon_index[level].extend(b_colon);
+            comma_index[level].clear();
+            comma_index[level].extend(b_comma);
+        }
+    }
     Ok(())
 }
 
+#[inline]
+fn clear_leveled_bitmap(b: &mut [u64], i: usize, m_rightbit: u64, j: usize, m_leftbit: u64) {
+    if i == j {
+        b[i] &= !(m_rightbit.wrapping_sub(m_leftbit));
+    } else {
+        b[j] &= m_leftbit.wrapping_sub(1);
+        b[i] &= !(m_rightbit.wrapping_sub(1));
+        for m in &mut b[j + 1..i] {
+            *m = 0;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::avx;
-    use super::super::utf8::QUOTE;
+    use super::super::utf8::{BACKSLASH, COLON, COMMA, QUOTE};
+
+    #[test]
+    fn test_build_structural_indices() {
+        let rec = r#"{"a": [1, {"b": 2, "c": "3,:"}], "d": {"e": [4, 5]}}"#.as_bytes();
+        let mut index_builder = IndexBuilder::new(3);
+        assert_eq!(Ok(()), index_builder.build_structural_indices(rec));
+
+        let positions = |b: &[u64], c: u8| {
+            (0..rec.len())
+                .filter(|&i| rec[i] == c && b[i / 64] & (1 << (i % 64)) != 0)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(vec![4, 36], positions(&index_builder.index[0], COLON));
+        assert_eq!(vec![4, 36, 42], positions(&index_builder.index[1], COLON));
+        assert_eq!(vec![4, 14, 22, 36, 42], positions(&index_builder.index[2], COLON));
+        assert_eq!(vec![31], positions(&index_builder.comma_index[0], COMMA));
+        assert_eq!(vec![8, 31], positions(&index_builder.comma_index[1], COMMA));
+        assert_eq!(vec![8, 17, 31, 46], positions(&index_builder.comma_index[2], COMMA));
+
+        let mut unbounded = IndexBuilder::unbounded();
+        assert_eq!(Ok(()), unbounded.build_structural_indices(rec));
+        assert_eq!(index_builder.index, unbounded.index);
+        assert_eq!(index_builder.comma_index, unbounded.comma_index);
+        assert_eq!(Ok(()), unbounded.build_structural_indices(br#"{"a": 1}"#));
+        assert_eq!(vec![vec![1 << 4]], unbounded.index);
+        assert_eq!(vec![vec![0]], unbounded.comma_index);
+    }
+
+    #[test]
+    fn test_build_structural_indices_max_depth() {
+        let deep = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        let mut index_builder = IndexBuilder::unbounded();
+        assert_eq!(DEFAULT_MAX_DEPTH, index_builder.max_depth());
+        assert_eq!(Err(Error::from(ErrorKind::TooDeep)), index_builder.build_structural_indices(deep.as_bytes()));
+
+        // the levels of a deep record are dropped by the following shallow one
+        index_builder.set_max_depth(200);
+        assert_eq!(Ok(()), index_builder.build_structural_indices(deep.as_bytes()));
+        assert_eq!(200, index_builder.index.len());
+        assert_eq!(200, index_builder.comma_index.len());
+        assert_eq!(Ok(()), index_builder.build_structural_indices(br#"{"a": [1, 2]}"#));
+        assert_eq!(vec![vec![1 << 4], vec![1 << 4]], index_builder.index);
+        assert_eq!(vec![vec![0], vec![1 << 8]], index_builder.comma_index);
+
+        // the levels deeper than a bounded index builder are not limited
+        let mut index_builder = IndexBuilder::new(2);
+        assert_eq!(Ok(()), index_builder.build_structural_indices(deep.as_bytes()));
+        assert_eq!(2, index_builder.index.len());
+    }
+
+    #[test]
+    fn test_split_records() {
+        struct TestCase<'a> {
+            s: &'a str,
+            want: Result<Vec<Range<usize>>>,
+        }
+        let long = format!(r#"{{"a": "{}"}}"#, "}".repeat(100));
+        let s = format!("{} \r\n{}\t[]", long, long);
+        let test_cases = vec![
+            TestCase {
+                s: "",
+                want: Ok(vec![]),
+            },
+            TestCase {
+                s: r#"{"a": [1, {"b": "]"}]}[{}]  {"c": "\"{"}"#,
+                want: Ok(vec![0..22, 22..26, 28..40]),
+            },
+            TestCase {
+                s: &s,
+                want: Ok(vec![0..long.len(), long.len() + 3..long.len() * 2 + 3, long.len() * 2 + 4..long.len() * 2 + 6]),
+            },
+            TestCase {
+                s: r#"{} 1 {}"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: r#"{}}"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: r#"{} {"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: r#"{} x"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+        ];
+        let mut index_builder = IndexBuilder::new(1);
+        for t in test_cases {
+            let mut records = Vec::new();
+            let got = index_builder.split_records(t.s.as_bytes(), &mut records).map(|_| records);
+            assert_eq!(t.want, got);
+        }
+    }
+
+    #[test]
+    fn test_contains_backslash() {
+        let mut rec = vec![b' '; 200];
+        rec[10] = BACKSLASH;
+        rec[130] = BACKSLASH;
+        let mut index_builder = IndexBuilder::new(1);
+        assert_eq!(Ok(()), index_builder.build_structural_indices(&rec));
+
+        assert!(!index_builder.contains_backslash(0, 0));
+        assert!(!index_builder.contains_backslash(0, 10));
+        assert!(index_builder.contains_backslash(0, 11));
+        assert!(index_builder.contains_backslash(10, 11));
+        assert!(!index_builder.contains_backslash(11, 130));
+        assert!(index_builder.contains_backslash(11, 131));
+        assert!(index_builder.contains_backslash(60, 200));
+        assert!(!index_builder.contains_backslash(131, 200));
+    }
+
+    #[test]
+    fn test_set_backend() {
+        let rec = br#"{"a": "x:y", "b": ["\"{", 1], "c": {"d": "}"}}"#;
+        let mut want = IndexBuilder::new(3);
+        want.set_backend(Backend::Scalar);
+        assert!(!want.clmul);
+        want.build_structural_indices(rec).unwrap();
+        for backend in Backend::supported() {
+            let mut index_builder = IndexBuilder::new(3);
+            index_builder.set_backend(backend);
+            index_builder.build_structural_indices(rec).unwrap();
+            assert_eq!(want.b_string_mask, index_builder.b_string_mask);
+            assert_eq!(want.index, index_builder.index);
+        }
+    }
 
     #[test]
     fn test_build_structural_character_bitmap() {
         let c = QUOTE;
-        let m = avx::mm256i(c as i8);
 
         macro_rules! s {
             ($( [ $c:expr ; $n:expr ] ),*) => {{
@@ -508,22 +728,20 @@ mod tests {
                 ],
             },
         ];
-        for t in test_cases {
-            let mut d = Vec::with_capacity((t.s.len() + 1) / 2);
-            build_structural_character_bitmap(
-                &t.s,
-                &mut d,
-                &mut vec![],
-                &mut vec![],
-                &mut vec![],
-                &mut vec![],
-                &m,
-                &m,
-                &m,
-                &m,
-                &m,
-            );
-            assert_eq!(t.d, d);
+        for backend in Backend::supported() {
+            for t in &test_cases {
+                let mut d = Vec::with_capacity((t.s.len() + 1) / 2);
+                backend.build_structural_character_bitmap(
+                    &t.s,
+                    &mut vec![],
+                    &mut d,
+                    &mut vec![],
+                    &mut vec![],
+                    &mut vec![],
+                    &mut vec![],
+                );
+                assert_eq!(t.d, d, "{:?}", backend);
+            }
         }
     }
 
@@ -844,6 +1062,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_structural_quote_bitmap_backslash_runs() {
+        for offset in 0..64 {
+            for run in 0..200 {
+                let mut s = vec![b'a'; offset];
+                s.extend(vec![BACKSLASH; run]);
+                s.push(QUOTE);
+                s.extend(vec![BACKSLASH; run + 1]);
+                s.push(QUOTE);
+                let n = s.len().div_ceil(64);
+                let mut b_backslash = vec![0u64; n];
+                let mut b_quote = vec![0u64; n];
+                let mut want = vec![0u64; n];
+                let mut consecutive_backslash_num = 0;
+                for (i, &c) in s.iter().enumerate() {
+                    if c == BACKSLASH {
+                        b_backslash[i / 64] |= 1 << (i % 64);
+                        consecutive_backslash_num += 1;
+                        continue;
+                    }
+                    if c == QUOTE {
+                        b_quote[i / 64] |= 1 << (i % 64);
+                        if consecutive_backslash_num % 2 == 0 {
+                            want[i / 64] |= 1 << (i % 64);
+                        }
+                    }
+                    consecutive_backslash_num = 0;
+                }
+                build_structural_quote_bitmap(&b_backslash, &mut b_quote);
+                assert_eq!(want, b_quote, "offset: {}, run: {}", offset, run);
+            }
+        }
+    }
+
     #[test]
     fn test_build_string_mask_bitmap() {
         struct TestCase {
@@ -1010,11 +1262,20 @@ mod tests {
             let mut b_string_mask = Vec::with_capacity(t.b_quote.len());
             build_string_mask_bitmap(&t.b_quote, &mut b_string_mask);
             assert_eq!(t.want, b_string_mask);
+
+            #[cfg(target_arch = "x86_64")]
+            {
+                if is_x86_feature_detected!("pclmulqdq") {
+                    b_string_mask.clear();
+                    unsafe { clmul::build_string_mask_bitmap(&t.b_quote, &mut b_string_mask) };
+                    assert_eq!(t.want, b_string_mask);
+                }
+            }
         }
     }
 
     #[test]
-    fn test_build_leveled_colon_bitmap() {
+    fn test_build_leveled_bitmap() {
         struct TestCase {
             b_colon: Vec<u64>,
             b_left: Vec<u64>,
@@ -1253,18 +1514,23 @@ mod tests {
             },
         ];
         for t in test_cases {
-            let mut index = vec![Vec::new(); t.l];
+            let mut colon_index = vec![Vec::new(); t.l];
+            let mut comma_index = vec![Vec::new(); t.l];
             let mut s_left = Vec::new();
-            let r = build_leveled_colon_bitmap(
+            let r = build_leveled_bitmap(
+                &t.b_colon,
                 &t.b_colon,
                 &t.b_left,
                 &t.b_right,
                 t.l,
+                usize::MAX,
                 &mut s_left,
-                &mut index,
+                &mut colon_index,
+                &mut comma_index,
             );
             assert_eq!(Ok(()), r);
-            assert_eq!(t.want, index);
+            assert_eq!(t.want, colon_index);
+            assert_eq!(t.want, comma_index);
         }
     }
 }
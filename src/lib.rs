@@ -1,27 +1,37 @@
 //! JSON parser which picks up values directly without performing tokenization
 extern crate fnv;
-#[cfg(feature = "avx-accel")]
-extern crate x86intrin;
 
-#[cfg(feature = "avx-accel")]
-mod avx;
-#[cfg(not(feature = "avx-accel"))]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2;
 mod emulated;
 mod bit;
+#[cfg(target_arch = "x86_64")]
+mod clmul;
 mod error;
 #[doc(hidden)]
 pub mod index_builder;
+mod metrics;
 #[doc(hidden)]
 pub mod parser;
 mod pikkr;
 #[doc(hidden)]
 pub mod query;
+mod records;
 mod result;
+#[doc(hidden)]
+pub mod simd;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod sse2;
+mod stats;
+mod unescape;
 mod utf8;
+mod value;
 
-#[cfg(not(feature = "avx-accel"))]
 pub use emulated::avx;
 
 pub use error::{Error, ErrorKind};
+pub use metrics::Metrics;
 pub use pikkr::Pikkr;
+pub use records::{Record, Records};
 pub use result::Result;
+pub use value::Value;
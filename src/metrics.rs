@@ -0,0 +1,27 @@
+/// Counters of the records parsed by a parser
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// The number of records parsed successfully
+    pub records: u64,
+
+    /// The number of records whose values were picked up by speculative parsing
+    pub speculative_hits: u64,
+
+    /// The number of records which fell back to basic parsing because the speculation failed
+    pub fallbacks: u64,
+
+    /// The number of records whose values were partly picked up by basic parsing without speculation
+    ///
+    /// The values matched by a wildcard or recursive descent are always picked up by basic parsing,
+    /// and such records are counted neither as speculative hits nor as fallbacks.
+    pub basic_parses: u64,
+
+    /// The number of times the speculation failed for each query, in the same order as the queries
+    ///
+    /// A failure is counted for a query if the field of the query or any of its parents was not found at the learned positions,
+    /// e.g. a record whose field `a` was not found is counted for both `$.a.b` and `$.a.c`.
+    pub query_misses: Vec<u64>,
+
+    /// The number of bytes of the records indexed by the parser, including the ones which failed to be parsed
+    pub bytes_indexed: u64,
+}
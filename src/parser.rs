@@ -3,149 +3,635 @@ use super::error::{Error, ErrorKind};
 use super::index_builder::IndexBuilder;
 use super::query::{QueryNode, QueryTree};
 use super::result::Result;
-use super::utf8::{COMMA, CR, HT, LF, RIGHT_BRACE, SPACE};
-use fnv::FnvHashSet;
-use std::cell::RefCell;
+use super::unescape::unescape;
+use super::utf8::{COMMA, CR, HT, LEFT_BRACE, LEFT_BRACKET, LF, RIGHT_BRACE, RIGHT_BRACKET, SPACE};
+use std::cmp;
+use std::ops::Range;
+use std::ptr;
+use std::sync::Arc;
 
+/// A receiver of the values picked up by the parser.
+pub trait Collector<'a> {
+    /// Receives the value `rec[si..ei + 1]` matched by the query path `path_id`.
+    fn collect(&mut self, path_id: usize, rec: &'a [u8], si: usize, ei: usize);
+
+    /// Discards all the received values.
+    fn clear(&mut self);
+}
+
+impl<'a> Collector<'a> for [Option<&'a [u8]>] {
+    #[inline]
+    fn collect(&mut self, path_id: usize, rec: &'a [u8], si: usize, ei: usize) {
+        // keep the value which appears first in the record
+        match self[path_id] {
+            Some(r) if r.as_ptr() <= rec[si..].as_ptr() => {}
+            _ => self[path_id] = Some(&rec[si..ei + 1]),
+        }
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        for r in self.iter_mut() {
+            *r = None;
+        }
+    }
+}
+
+impl<'a> Collector<'a> for [Option<Range<usize>>] {
+    #[inline]
+    fn collect(&mut self, path_id: usize, _rec: &'a [u8], si: usize, ei: usize) {
+        // keep the value which appears first in the record
+        match self[path_id] {
+            Some(ref r) if r.start <= si => {}
+            _ => self[path_id] = Some(si..ei + 1),
+        }
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        for r in self.iter_mut() {
+            *r = None;
+        }
+    }
+}
+
+impl<'a> Collector<'a> for [Vec<&'a [u8]>] {
+    #[inline]
+    fn collect(&mut self, path_id: usize, rec: &'a [u8], si: usize, ei: usize) {
+        self[path_id].push(&rec[si..ei + 1]);
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        for r in self.iter_mut() {
+            r.clear();
+        }
+    }
+}
+
+/// The positions learned for a node of the query tree, ranked by the number of times each of them was observed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Candidates {
+    positions: Vec<(usize, u64)>,
+}
+
+impl Candidates {
+    /// Returns the number of positions.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns whether no position has been observed or not.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Returns the number of times the position `i` was observed.
+    #[inline]
+    pub fn count(&self, i: usize) -> u64 {
+        self.positions.iter().find(|&&(p, _)| p == i).map_or(0, |&(_, n)| n)
+    }
+
+    /// Returns an iterator over the positions and their counts, from the most frequent one.
+    ///
+    /// The positions observed the same number of times are in the order they reached the number.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.positions.iter().cloned()
+    }
+
+    /// Counts an observation of the position `i`.
+    #[inline]
+    pub fn observe(&mut self, i: usize) {
+        self.add(i, 1);
+    }
+
+    /// Counts `n` observations of the position `i`, moving it ahead of the less frequent positions.
+    #[inline]
+    pub fn add(&mut self, i: usize, n: u64) {
+        let mut k = match self.positions.iter().position(|&(p, _)| p == i) {
+            Some(k) => k,
+            None => {
+                self.positions.push((i, 0));
+                self.positions.len() - 1
+            }
+        };
+        self.positions[k].1 = self.positions[k].1.saturating_add(n);
+        while k > 0 && self.positions[k - 1].1 < self.positions[k].1 {
+            self.positions.swap(k - 1, k);
+            k -= 1;
+        }
+    }
+
+    /// Appends the position `i` observed `n` times, which must not be in the candidates or more frequent than the last one.
+    #[inline]
+    pub(crate) fn push(&mut self, i: usize, n: u64) {
+        debug_assert!(self.positions.last().is_none_or(|&(_, m)| m >= n));
+        self.positions.push((i, n));
+    }
+}
+
+/// The outcome of parsing a record, ordered from the most successful speculation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Speculation {
+    /// All the values were picked up at the learned positions.
+    Hit,
+    /// Some values were picked up by basic parsing without any speculation, because they are matched by a wildcard or recursive descent.
+    Basic,
+    /// The speculation failed and the record was parsed again by basic parsing.
+    Miss,
+}
+
+#[derive(Clone)]
 pub struct Parser {
     pub index_builder: IndexBuilder,
-    stats: Vec<FnvHashSet<usize>>,
-    colon_positions: RefCell<Vec<Vec<usize>>>,
+    stats: Arc<Vec<Candidates>>,
+    // the positions observed by a forked parser apart from the shared ones
+    forked_stats: Option<Vec<Candidates>>,
+    online: bool,
+    strict: bool,
+    misses: Vec<u64>,
+    found: Vec<usize>,
+    found_saved: Vec<(usize, usize)>,
+    colon_positions: Vec<Vec<usize>>,
+    comma_positions: Vec<Vec<usize>>,
+    field_buf: Vec<u8>,
 }
 
 impl Parser {
     pub fn new(queries: &QueryTree) -> Self {
-        let index_builder = IndexBuilder::new(queries.max_level());
-        let colon_positions = RefCell::new(vec![Vec::new(); queries.max_level()]);
+        let index_builder = if queries.is_recursive() {
+            IndexBuilder::unbounded()
+        } else {
+            IndexBuilder::new(queries.max_level())
+        };
+        let colon_positions = vec![Vec::new(); queries.max_level()];
+        let comma_positions = vec![Vec::new(); queries.max_level()];
         let stats = vec![Default::default(); queries.num_nodes()];
+        let misses = vec![0; queries.num_nodes()];
+        let found = vec![usize::MAX; queries.num_nodes()];
         Self {
             index_builder,
-            stats,
+            stats: Arc::new(stats),
+            forked_stats: None,
+            online: false,
+            strict: false,
+            misses,
+            found,
+            found_saved: Vec::new(),
             colon_positions,
+            comma_positions,
+            field_buf: Vec::new(),
+        }
+    }
+
+    /// Creates a parser for another thread, which shares the positions learned by this parser read-only.
+    ///
+    /// The positions observed by the forked parser are kept apart until they are merged by `merge`.
+    pub fn fork(&self, queries: &QueryTree) -> Self {
+        let mut parser = Self::new(queries);
+        parser.stats = Arc::clone(&self.stats);
+        parser.forked_stats = Some(vec![Default::default(); self.stats.len()]);
+        parser.online = self.online;
+        parser.strict = self.strict;
+        parser
+    }
+
+    /// Returns the positions learned for each node of the query tree.
+    pub fn stats(&self) -> &[Candidates] {
+        &self.stats
+    }
+
+    /// Replaces the positions learned for each node of the query tree.
+    pub fn set_stats(&mut self, stats: Vec<Candidates>) {
+        self.stats = Arc::new(stats);
+    }
+
+    /// Adds the positions observed and the speculation failures counted by the parsers forked from this parser.
+    pub fn merge(&mut self, forks: Vec<Parser>) {
+        // the forks are dropped first so that the shared positions are not copied
+        let forks = forks
+            .into_iter()
+            .map(|f| (f.forked_stats.unwrap_or_default(), f.misses))
+            .collect::<Vec<_>>();
+        let stats = Arc::make_mut(&mut self.stats);
+        for (forked_stats, misses) in forks {
+            for (s, o) in stats.iter_mut().zip(&forked_stats) {
+                for (i, n) in o.iter() {
+                    s.add(i, n);
+                }
+            }
+            for (m, o) in self.misses.iter_mut().zip(&misses) {
+                *m += o;
+            }
         }
     }
 
+    /// Counts the position `i` observed for the node `id`.
     #[inline]
-    pub fn basic_parse<'a>(&mut self, rec: &'a [u8], queries: &QueryNode, start: usize, end: usize, set_stats: bool, results: &mut Vec<Option<&'a [u8]>>) -> Result<()> {
-        let level = queries.level();
+    fn observe(&mut self, id: usize, i: usize) {
+        match self.forked_stats {
+            Some(ref mut stats) => stats[id].observe(i),
+            None => Arc::make_mut(&mut self.stats)[id].observe(i),
+        }
+    }
+
+    /// Sets whether the positions matched by speculative parsing are also counted or not, which is `false` by default.
+    pub fn set_online(&mut self, online: bool) {
+        self.online = online;
+    }
+
+    /// Sets whether speculative parsing verifies that the matched field is not followed by another one with the same name or not, which is `false` by default.
+    ///
+    /// The results of speculative parsing are identical to the ones of basic parsing, which picks up the last one of the duplicate fields, if this is `true`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Returns the number of times the speculation failed at each node of the query tree.
+    pub fn misses(&self) -> &[u64] {
+        &self.misses
+    }
 
-        generate_colon_positions(
+    /// Resets the number of times the speculation failed.
+    pub fn reset_misses(&mut self) {
+        for m in &mut self.misses {
+            *m = 0;
+        }
+    }
+
+    /// Picks up the values of `queries` from the record `rec`.
+    ///
+    /// The positions of the values are learned by basic parsing if `train` is `true`,
+    /// and used by speculative parsing, which falls back to basic parsing, otherwise.
+    /// This function will return the outcome of the speculation, which is `Speculation::Basic` when training.
+    #[inline]
+    pub fn parse<'a, C: ?Sized + Collector<'a>>(&mut self, rec: &'a [u8], queries: &QueryTree, train: bool, results: &mut C) -> Result<Speculation> {
+        if rec.is_empty() {
+            return Err(Error::from(ErrorKind::InvalidRecord));
+        }
+
+        self.index_builder.build_structural_indices(rec)?;
+
+        let end = rec.len() - 1;
+        if train {
+            self.basic_parse(rec, queries.as_node(), 0, end, true, results)?;
+            return Ok(Speculation::Basic);
+        }
+        let speculation = self.speculative_parse(rec, queries.as_node(), 0, end, results)?;
+        if speculation != Speculation::Miss {
+            return Ok(speculation);
+        }
+        results.clear();
+        self.basic_parse(rec, queries.as_node(), 0, end, false, results)?;
+        Ok(Speculation::Miss)
+    }
+
+    /// Picks up the values of `queries` by scanning the value `rec[start..end + 1]`.
+    ///
+    /// The values are passed to `results` in the reverse order of their positions.
+    /// Only the last one of the fields with the same name in an object is picked up.
+    #[inline]
+    pub fn basic_parse<'a, C: ?Sized + Collector<'a>>(&mut self, rec: &'a [u8], queries: &QueryNode, start: usize, end: usize, set_stats: bool, results: &mut C) -> Result<()> {
+        for f in &mut self.found {
+            *f = usize::MAX;
+        }
+        self.found_saved.clear();
+        self.basic_walk(rec, queries, queries.descendant(), queries.level(), start, end, set_stats, results)
+    }
+
+    /// Picks up the values of the children of `queries` from the members or elements of the value `rec[start..end + 1]` at `level`.
+    ///
+    /// The children of `descendant` are also picked up from this value and all the values nested in it.
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    fn basic_walk<'a, C: ?Sized + Collector<'a>>(&mut self, rec: &'a [u8], queries: &QueryNode, descendant: Option<&QueryNode>, level: usize, start: usize, end: usize, set_stats: bool, results: &mut C) -> Result<()> {
+        if level >= self.index_builder.index.len() {
+            // the value is a scalar nested deeper than any object or array of the record
+            return Ok(());
+        }
+
+        if queries.num_elements() > 0 || queries.wildcard().is_some() || descendant.is_some() {
+            let si = skip_whitespaces(rec, start);
+            if si <= end && rec[si] == LEFT_BRACKET {
+                return self.basic_walk_elements(rec, queries, descendant, level, si, end, set_stats, results);
+            }
+        }
+
+        // the children of `descendant` have to be picked up separately unless `queries` is `descendant` itself
+        let extra = descendant.filter(|d| !ptr::eq(*d, queries));
+
+        generate_positions(
             &self.index_builder.index,
             start,
             end,
             level,
-            &mut *self.colon_positions.borrow_mut(),
+            &mut self.colon_positions,
         );
 
+        // the nodes found in this object are restored on return, since a recursive descent walks the same nodes in the nested objects
+        let saved = self.found_saved.len();
         let mut found_num = 0;
         let mut vei = end;
-        let cp_len = self.colon_positions.borrow()[level].len();
+        let cp_len = self.colon_positions[level].len();
         for i in (0..cp_len).rev() {
             let (fsi, fei) = search_pre_field_indices(
                 &self.index_builder.b_quote,
                 if i > 0 {
-                    self.colon_positions.borrow()[level][i - 1]
+                    self.colon_positions[level][i - 1]
                 } else {
                     start
                 },
-                self.colon_positions.borrow()[level][i],
+                self.colon_positions[level][i],
             )?;
-            let field = &rec[fsi + 1..fei];
-            if let Some(query) = queries.get_child(field) {
+            let field = field_name(&self.index_builder, &mut self.field_buf, rec, fsi, fei);
+            let query = queries.get_child(field);
+            let extra_query = extra.and_then(|d| d.get_child(field)).filter(|q| self.found[q.id()] != start);
+            if query.is_some() || queries.wildcard().is_some() || descendant.is_some() {
                 let (vsi, vei) = search_post_value_indices(
                     rec,
-                    self.colon_positions.borrow()[level][i] + 1,
+                    self.colon_positions[level][i] + 1,
                     vei,
                     if i == cp_len - 1 { RIGHT_BRACE } else { COMMA },
                 )?;
-                found_num += 1;
-                if set_stats && !self.stats[query.id()].contains(&i) {
-                    self.stats[query.id()].insert(i);
-                }
-                if !query.is_leaf() {
-                    self.basic_parse(rec, query, vsi, vei, set_stats, results)?;
+                if let Some(d) = descendant {
+                    self.basic_descend(rec, d, level + 1, vsi, vei, set_stats, results)?;
+                    if let Some(query) = extra_query {
+                        self.stamp_found(query.id(), start);
+                        self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
+                    }
+                    if let Some(query) = extra.and_then(|d| d.wildcard()) {
+                        self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
+                    }
                 }
-                if let Some(i) = query.path_id() {
-                    results[i] = Some(&rec[vsi..vei + 1]);
+                if let Some(query) = queries.wildcard() {
+                    self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
                 }
-                if found_num == queries.num_children() {
-                    return Ok(());
+                // the objects walked with the same node are told apart by their start positions
+                if let Some(query) = query.filter(|q| self.found[q.id()] != start) {
+                    self.stamp_found(query.id(), start);
+                    found_num += 1;
+                    if set_stats {
+                        self.observe(query.id(), i);
+                    }
+                    self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
+                    if found_num == queries.num_children() && queries.wildcard().is_none() && descendant.is_none() {
+                        break;
+                    }
                 }
             }
             vei = fsi - 1;
         }
+        self.restore_found(saved);
+        Ok(())
+    }
+
+    /// Marks the node `id` as found in the object starting at `start`, saving the previous mark.
+    #[inline]
+    fn stamp_found(&mut self, id: usize, start: usize) {
+        self.found_saved.push((id, self.found[id]));
+        self.found[id] = start;
+    }
+
+    /// Restores the marks saved after the first `len` ones.
+    #[inline]
+    fn restore_found(&mut self, len: usize) {
+        while self.found_saved.len() > len {
+            let (id, start) = self.found_saved.pop().unwrap();
+            self.found[id] = start;
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    fn basic_walk_elements<'a, C: ?Sized + Collector<'a>>(&mut self, rec: &'a [u8], queries: &QueryNode, descendant: Option<&QueryNode>, level: usize, start: usize, end: usize, set_stats: bool, results: &mut C) -> Result<()> {
+        let extra = descendant.filter(|d| !ptr::eq(*d, queries));
+
+        generate_positions(
+            &self.index_builder.comma_index,
+            start,
+            end,
+            level,
+            &mut self.comma_positions,
+        );
+
+        if queries.wildcard().is_none() && descendant.is_none() {
+            for (&i, query) in queries.iter_elements() {
+                let indices = search_element_indices(rec, &self.comma_positions[level], start, end, i)?;
+                if let Some((vsi, vei)) = indices {
+                    self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let cp_len = self.comma_positions[level].len();
+        for i in (0..cp_len + 1).rev() {
+            let indices = search_element_indices(rec, &self.comma_positions[level], start, end, i)?;
+            let (vsi, vei) = match indices {
+                Some(indices) => indices,
+                None => continue,
+            };
+            if let Some(d) = descendant {
+                self.basic_descend(rec, d, level + 1, vsi, vei, set_stats, results)?;
+                if let Some(query) = extra.and_then(|d| d.get_element(i)) {
+                    self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
+                }
+                if let Some(query) = extra.and_then(|d| d.wildcard()) {
+                    self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
+                }
+            }
+            if let Some(query) = queries.wildcard() {
+                self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
+            }
+            if let Some(query) = queries.get_element(i) {
+                self.basic_parse_value(rec, query, level + 1, vsi, vei, set_stats, results)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks up the values of the children of `descendant` from all the values nested in the value `rec[vsi..vei + 1]`, if it is an object or array.
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    fn basic_descend<'a, C: ?Sized + Collector<'a>>(&mut self, rec: &'a [u8], descendant: &QueryNode, level: usize, vsi: usize, vei: usize, set_stats: bool, results: &mut C) -> Result<()> {
+        match rec[vsi] {
+            LEFT_BRACE | LEFT_BRACKET => self.basic_walk(rec, descendant, Some(descendant), level, vsi, vei, set_stats, results),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    fn basic_parse_value<'a, C: ?Sized + Collector<'a>>(&mut self, rec: &'a [u8], query: &QueryNode, level: usize, vsi: usize, vei: usize, set_stats: bool, results: &mut C) -> Result<()> {
+        if !query.is_leaf() {
+            self.basic_walk(rec, query, query.descendant(), level, vsi, vei, set_stats, results)?;
+        }
+        for &i in query.path_ids() {
+            results.collect(i, rec, vsi, vei);
+        }
         Ok(())
     }
 
+    /// Picks up the values of `queries` from the value `rec[start..end + 1]` by using the learned positions.
+    ///
+    /// This function will return `Speculation::Miss` if the speculation fails, in which case `results` may have been partially filled.
+    /// The speculation goes on for the other nodes after a failure, so that the failures are counted for all of them.
     #[inline]
-    pub fn speculative_parse<'a>(&self, rec: &'a [u8], queries: &QueryNode, start: usize, end: usize, results: &mut Vec<Option<&'a [u8]>>) -> Result<bool> {
+    pub fn speculative_parse<'a, C: ?Sized + Collector<'a>>(&mut self, rec: &'a [u8], queries: &QueryNode, start: usize, end: usize, results: &mut C) -> Result<Speculation> {
         let level = queries.level();
 
-        generate_colon_positions(
+        if queries.wildcard().is_some() || queries.descendant().is_some() {
+            // The values matched by a wildcard or recursive descent have no fixed positions to speculate on.
+            self.basic_parse(rec, queries, start, end, false, results)?;
+            return Ok(Speculation::Basic);
+        }
+
+        if queries.num_elements() > 0 {
+            let si = skip_whitespaces(rec, start);
+            if si <= end && rec[si] == LEFT_BRACKET {
+                return self.speculative_parse_elements(rec, queries, si, end, results);
+            }
+        }
+
+        generate_positions(
             &self.index_builder.index,
             start,
             end,
             level,
-            &mut *self.colon_positions.borrow_mut(),
+            &mut self.colon_positions,
         );
 
-        for (&s, q) in queries.iter() {
-            let mut found = false;
-            for &i in &self.stats[q.id()] {
-                let cp_len = self.colon_positions.borrow()[level].len();
+        let mut speculation = Speculation::Hit;
+        for (s, q) in queries.iter() {
+            let mut indices = None;
+            let mut matched = 0;
+            for (i, _) in self.stats[q.id()].iter() {
+                let cp_len = self.colon_positions[level].len();
                 if i >= cp_len {
                     continue;
                 }
                 let (fsi, fei) = search_pre_field_indices(
                     &self.index_builder.b_quote,
                     if i > 0 {
-                        self.colon_positions.borrow()[level][i - 1]
+                        self.colon_positions[level][i - 1]
                     } else {
                         start
                     },
-                    self.colon_positions.borrow()[level][i],
+                    self.colon_positions[level][i],
                 )?;
-                let field = &rec[fsi + 1..fei];
-                if s == field {
+                let field = field_name(&self.index_builder, &mut self.field_buf, rec, fsi, fei);
+                if **s == *field {
                     let vei = if i < cp_len - 1 {
                         let (nfsi, _) = search_pre_field_indices(
                             &self.index_builder.b_quote,
-                            self.colon_positions.borrow()[level][i],
-                            self.colon_positions.borrow()[level][i + 1],
+                            self.colon_positions[level][i],
+                            self.colon_positions[level][i + 1],
                         )?;
                         nfsi - 1
                     } else {
                         end
                     };
-                    let (vsi, vei) = search_post_value_indices(
+                    indices = Some(search_post_value_indices(
                         rec,
-                        self.colon_positions.borrow()[level][i] + 1,
+                        self.colon_positions[level][i] + 1,
                         vei,
                         if i == cp_len - 1 { RIGHT_BRACE } else { COMMA },
-                    )?;
-                    if !q.is_leaf() {
-                        found = self.speculative_parse(rec, q, vsi, vei, results)?;
-                    } else {
-                        found = true;
-                    }
-                    if let Some(i) = q.path_id() {
-                        results[i] = Some(&rec[vsi..vei + 1]);
-                    }
+                    )?);
+                    matched = i;
                     break;
                 }
             }
-            if !found {
-                return Ok(false);
+            if indices.is_some() && self.strict && self.contains_field(rec, s, level, matched + 1)? {
+                indices = None;
+            }
+            let (vsi, vei) = match indices {
+                Some(indices) => indices,
+                None => {
+                    self.misses[q.id()] += 1;
+                    speculation = Speculation::Miss;
+                    continue;
+                }
+            };
+            if self.online {
+                self.observe(q.id(), matched);
+            }
+            if !q.is_leaf() {
+                let nested = self.speculative_parse(rec, q, vsi, vei, results)?;
+                speculation = cmp::max(speculation, nested);
+                if nested == Speculation::Miss {
+                    continue;
+                }
+            }
+            for &i in q.path_ids() {
+                results.collect(i, rec, vsi, vei);
+            }
+        }
+        Ok(speculation)
+    }
+
+    /// Returns whether any of the fields after the `i`-th colon at `level` is named `field` or not.
+    #[inline]
+    fn contains_field(&mut self, rec: &[u8], field: &[u8], level: usize, i: usize) -> Result<bool> {
+        for j in i..self.colon_positions[level].len() {
+            let (fsi, fei) = search_pre_field_indices(
+                &self.index_builder.b_quote,
+                self.colon_positions[level][j - 1],
+                self.colon_positions[level][j],
+            )?;
+            if field == field_name(&self.index_builder, &mut self.field_buf, rec, fsi, fei) {
+                return Ok(true);
             }
         }
-        Ok(true)
+        Ok(false)
+    }
+
+    /// Picks up the array elements, which does not need any speculation since their positions are determined by commas.
+    #[inline]
+    fn speculative_parse_elements<'a, C: ?Sized + Collector<'a>>(&mut self, rec: &'a [u8], queries: &QueryNode, start: usize, end: usize, results: &mut C) -> Result<Speculation> {
+        let level = queries.level();
+
+        generate_positions(
+            &self.index_builder.comma_index,
+            start,
+            end,
+            level,
+            &mut self.comma_positions,
+        );
+
+        let mut speculation = Speculation::Hit;
+        for (&i, q) in queries.iter_elements() {
+            let indices = search_element_indices(rec, &self.comma_positions[level], start, end, i)?;
+            if let Some((vsi, vei)) = indices {
+                if !q.is_leaf() {
+                    let nested = self.speculative_parse(rec, q, vsi, vei, results)?;
+                    speculation = cmp::max(speculation, nested);
+                    if nested == Speculation::Miss {
+                        continue;
+                    }
+                }
+                for &i in q.path_ids() {
+                    results.collect(i, rec, vsi, vei);
+                }
+            }
+        }
+        Ok(speculation)
     }
 }
 
 #[inline]
-fn generate_colon_positions(index: &[Vec<u64>], start: usize, end: usize, level: usize, colon_positions: &mut Vec<Vec<usize>>) {
-    let cp = &mut colon_positions[level];
+fn generate_positions(index: &[Vec<u64>], start: usize, end: usize, level: usize, positions: &mut Vec<Vec<usize>>) {
+    if positions.len() <= level {
+        positions.resize(level + 1, Vec::new());
+    }
+    let cp = &mut positions[level];
     cp.clear();
+    if level >= index.len() {
+        // the record has no object or array at this level
+        return;
+    }
     for i in start / 64..(end + 63) / 64 {
         let mut m_colon = index[level][i];
         while m_colon != 0 {
@@ -159,6 +645,22 @@ fn generate_colon_positions(index: &[Vec<u64>], start: usize, end: usize, level:
     }
 }
 
+/// Returns the field name `rec[fsi + 1..fei]`, whose escape sequences are decoded into `buf` if any.
+///
+/// A field name with invalid escape sequences is returned as it is.
+#[inline]
+fn field_name<'a>(index_builder: &IndexBuilder, buf: &'a mut Vec<u8>, rec: &'a [u8], fsi: usize, fei: usize) -> &'a [u8] {
+    let field = &rec[fsi + 1..fei];
+    if !index_builder.contains_backslash(fsi + 1, fei) {
+        return field;
+    }
+    buf.clear();
+    if unescape(field, buf).is_err() {
+        return field;
+    }
+    buf
+}
+
 #[inline]
 fn search_pre_field_indices(b_quote: &[u64], start: usize, end: usize) -> Result<(usize, usize)> {
     let mut si = 0;
@@ -198,6 +700,46 @@ fn search_pre_field_indices(b_quote: &[u64], start: usize, end: usize) -> Result
     }
 }
 
+/// Searches the indices of the `i`-th element of the array which starts at `start`.
+///
+/// This function will return a `None` if the array does not have the `i`-th element.
+#[inline]
+fn search_element_indices(rec: &[u8], comma_positions: &[usize], start: usize, end: usize, i: usize) -> Result<Option<(usize, usize)>> {
+    let n = comma_positions.len();
+    if i > n {
+        return Ok(None);
+    }
+    if n == 0 {
+        let si = skip_whitespaces(rec, start + 1);
+        if si <= end && rec[si] == RIGHT_BRACKET {
+            return Ok(None);
+        }
+    }
+    let si = if i > 0 { comma_positions[i - 1] + 1 } else { start + 1 };
+    let indices = if i < n {
+        search_post_value_indices(rec, si, comma_positions[i], COMMA)?
+    } else {
+        search_post_value_indices(rec, si, end, RIGHT_BRACKET)?
+    };
+    Ok(Some(indices))
+}
+
+#[inline]
+fn skip_whitespaces(rec: &[u8], i: usize) -> usize {
+    let mut i = i;
+    while i < rec.len() {
+        match rec[i] {
+            SPACE | HT | LF | CR => {
+                i += 1;
+            }
+            _ => {
+                break;
+            }
+        }
+    }
+    i
+}
+
 #[inline]
 fn search_post_value_indices(rec: &[u8], si: usize, ei: usize, ignore_once_char: u8) -> Result<(usize, usize)> {
     let mut si = si;
@@ -264,7 +806,7 @@ mod tests {
             0,
             json_rec.len() - 1,
             true,
-            &mut results,
+            &mut results[..],
         );
 
         assert_eq!(Ok(()), result);
@@ -277,7 +819,167 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_colon_positions() {
+    fn test_candidates() {
+        struct TestCase {
+            observed: Vec<usize>,
+            want: Vec<(usize, u64)>,
+        }
+        let test_cases = vec![
+            TestCase {
+                observed: vec![],
+                want: vec![],
+            },
+            TestCase {
+                observed: vec![3, 1, 1],
+                want: vec![(1, 2), (3, 1)],
+            },
+            TestCase {
+                observed: vec![3, 1, 2, 2, 1],
+                want: vec![(2, 2), (1, 2), (3, 1)],
+            },
+            TestCase {
+                observed: vec![0, 0, 5, 5, 5, 0],
+                want: vec![(5, 3), (0, 3)],
+            },
+        ];
+        for t in test_cases {
+            let mut c = Candidates::default();
+            for &i in &t.observed {
+                c.observe(i);
+            }
+            assert_eq!(t.want, c.iter().collect::<Vec<_>>());
+            assert_eq!(t.want.len(), c.len());
+        }
+    }
+
+    #[test]
+    fn test_fork_merge() {
+        let queries = QueryTree::new(&["$.a", "$.b"]).unwrap();
+        let a = queries.as_node().get_child(b"a").unwrap().id();
+        let b = queries.as_node().get_child(b"b").unwrap().id();
+        let mut parser = Parser::new(&queries);
+        let mut results: Vec<Option<&[u8]>> = vec![None; 2];
+        parser.parse(br#"{"a": 1, "b": 2}"#, &queries, true, &mut results[..]).unwrap();
+
+        let mut forks = vec![parser.fork(&queries), parser.fork(&queries)];
+        assert!(forks.iter().all(|f| Arc::ptr_eq(&parser.stats, &f.stats)));
+        forks[0].parse(br#"{"b": 3, "a": 4}"#, &queries, true, &mut results[..]).unwrap();
+        forks[1].parse(br#"{"b": 5, "a": 6}"#, &queries, true, &mut results[..]).unwrap();
+        forks[1].parse(br#"{"x": 7, "b": 8}"#, &queries, false, &mut results[..]).unwrap();
+        // the shared positions are not changed until merged
+        assert_eq!(vec![(0, 1)], forks[1].stats()[a].iter().collect::<Vec<_>>());
+
+        parser.merge(forks);
+        assert_eq!(vec![(1, 2), (0, 1)], parser.stats()[a].iter().collect::<Vec<_>>());
+        assert_eq!(vec![(0, 2), (1, 1)], parser.stats()[b].iter().collect::<Vec<_>>());
+        assert_eq!(1, parser.misses()[a]);
+    }
+
+    #[test]
+    fn test_basic_parse_elements() {
+        let json_rec_str = r#"{ "aaa" : [ "A0", { "a1": [10, 11] }, [] ], "bbb": [], "ccc": [ 333 ] }"#;
+        let json_rec = json_rec_str.as_bytes();
+        let query_strs = &["$.aaa[0]", "$.aaa[1].a1[1]", "$.aaa[2]", "$.aaa[2][0]", "$.aaa[3]", "$.bbb[0]", "$.ccc[0]"];
+
+        let queries = QueryTree::new(query_strs).unwrap();
+
+        let mut parser = Parser::new(&queries);
+        let r = parser.index_builder.build_structural_indices(json_rec);
+        assert_eq!(Ok(()), r);
+
+        let mut results = vec![None; query_strs.len()];
+        let result = parser.basic_parse(
+            json_rec,
+            &queries.as_node(),
+            0,
+            json_rec.len() - 1,
+            true,
+            &mut results[..],
+        );
+
+        assert_eq!(Ok(()), result);
+        assert_eq!(Some(r#""A0""#.as_bytes()), results[0]);
+        assert_eq!(Some(r#"11"#.as_bytes()), results[1]);
+        assert_eq!(Some(r#"[]"#.as_bytes()), results[2]);
+        assert_eq!(None, results[3]);
+        assert_eq!(None, results[4]);
+        assert_eq!(None, results[5]);
+        assert_eq!(Some(r#"333"#.as_bytes()), results[6]);
+    }
+
+    #[test]
+    fn test_search_element_indices() {
+        struct TestCase<'a> {
+            rec: &'a str,
+            comma_positions: Vec<usize>,
+            i: usize,
+            want: Result<Option<(usize, usize)>>,
+        }
+        let test_cases = vec![
+            TestCase {
+                rec: "[]",
+                comma_positions: vec![],
+                i: 0,
+                want: Ok(None),
+            },
+            TestCase {
+                rec: "[ \n ]",
+                comma_positions: vec![],
+                i: 0,
+                want: Ok(None),
+            },
+            TestCase {
+                rec: "[1]",
+                comma_positions: vec![],
+                i: 0,
+                want: Ok(Some((1, 1))),
+            },
+            TestCase {
+                rec: "[1]",
+                comma_positions: vec![],
+                i: 1,
+                want: Ok(None),
+            },
+            TestCase {
+                rec: "[ 1 , 22 , 333 ]",
+                comma_positions: vec![4, 9],
+                i: 0,
+                want: Ok(Some((2, 2))),
+            },
+            TestCase {
+                rec: "[ 1 , 22 , 333 ]",
+                comma_positions: vec![4, 9],
+                i: 1,
+                want: Ok(Some((6, 7))),
+            },
+            TestCase {
+                rec: "[ 1 , 22 , 333 ]",
+                comma_positions: vec![4, 9],
+                i: 2,
+                want: Ok(Some((11, 13))),
+            },
+            TestCase {
+                rec: "[ 1 , 22 , 333 ]",
+                comma_positions: vec![4, 9],
+                i: 3,
+                want: Ok(None),
+            },
+            TestCase {
+                rec: "[1,]",
+                comma_positions: vec![2],
+                i: 1,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+        ];
+        for t in test_cases {
+            let rec = t.rec.as_bytes();
+            let got = search_element_indices(rec, &t.comma_positions, 0, rec.len() - 1, t.i);
+            assert_eq!(t.want, got);
+        }
+    }
+
+    #[test]
+    fn test_generate_positions() {
         struct TestCase {
             index: Vec<Vec<u64>>,
             start: usize,
@@ -319,7 +1021,7 @@ mod tests {
         ];
         for t in test_cases {
             let mut cp = vec![Vec::new(); t.level + 1];
-            generate_colon_positions(&t.index, t.start, t.end, t.level, &mut cp);
+            generate_positions(&t.index, t.start, t.end, t.level, &mut cp);
             assert_eq!(t.want, cp[0]);
         }
     }
@@ -1,9 +1,21 @@
-use super::error::{Error, ErrorKind};
-use super::parser::Parser;
+use super::index_builder::IndexBuilder;
+use super::metrics::Metrics;
+use super::parser::{Collector, Parser, Speculation};
 use super::query::QueryTree;
+use super::records::Records;
 use super::result::Result;
+use super::stats;
+use std::cmp;
+use std::io::BufRead;
+use std::ops::Range;
+use std::panic;
+use std::thread;
 
 /// JSON parser which picks up values directly without performing tokenization
+///
+/// A clone of a parser starts from the training state of the original one,
+/// so that a trained parser can be copied to each worker of a thread pool.
+#[derive(Clone)]
 pub struct Pikkr<'a> {
     queries: QueryTree<'a>,
     parser: Parser,
@@ -11,6 +23,13 @@ pub struct Pikkr<'a> {
     train_num: usize,
     trained_num: usize,
     trained: bool,
+
+    retrain_window: usize,
+    retrain_threshold: f64,
+    speculated_num: usize,
+    missed_num: usize,
+
+    metrics: Metrics,
 }
 
 impl<'a> Pikkr<'a> {
@@ -18,74 +37,353 @@ impl<'a> Pikkr<'a> {
     #[inline]
     pub fn new<S: ?Sized + AsRef<[u8]>>(query_strs: &[&'a S], train_num: usize) -> Result<Pikkr<'a>> {
         let queries = QueryTree::new(query_strs)?;
+        Ok(Pikkr::from_queries(queries, train_num))
+    }
+
+    /// Creates a JSON parser which owns the query strings, and returns it.
+    ///
+    /// This is useful when the query strings do not outlive the parser, e.g. they are loaded from a configuration.
+    #[inline]
+    pub fn new_owned<S: Into<Vec<u8>>, I: IntoIterator<Item = S>>(query_strs: I, train_num: usize) -> Result<Pikkr<'static>> {
+        let queries = QueryTree::new_owned(query_strs)?;
+        Ok(Pikkr::from_queries(queries, train_num))
+    }
+
+    #[inline]
+    fn from_queries(queries: QueryTree<'a>, train_num: usize) -> Pikkr<'a> {
         let parser = Parser::new(&queries);
 
-        Ok(Pikkr {
+        Pikkr {
             queries,
             parser,
 
             train_num,
             trained_num: 0,
             trained: false,
-        })
+
+            retrain_window: 0,
+            retrain_threshold: 0.0,
+            speculated_num: 0,
+            missed_num: 0,
+
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Makes the parser retrain itself when more than `threshold` (between 0.0 and 1.0) of every `window` records
+    /// fail in speculative parsing and fall back to basic parsing, e.g. because the structure of the records has changed.
+    ///
+    /// The positions learned by retraining are added to the ones learned so far. Retraining is disabled if `window` is zero, which is the default.
+    #[inline]
+    pub fn set_retraining(&mut self, window: usize, threshold: f64) {
+        self.retrain_window = window;
+        self.retrain_threshold = threshold;
+        self.speculated_num = 0;
+        self.missed_num = 0;
+    }
+
+    /// Sets whether the positions matched by speculative parsing are also learned or not, which is `false` by default.
+    ///
+    /// The positions are tried in the order of the number of records they were observed in, so learning them online lets the parser follow the layout which becomes common after the training.
+    #[inline]
+    pub fn set_online_learning(&mut self, online: bool) {
+        self.parser.set_online(online);
+    }
+
+    /// Sets whether speculative parsing is verified to return the same values as basic parsing or not, which is `false` by default.
+    ///
+    /// Speculative parsing picks up the first field matched at a learned position, which may not be the last one of the duplicate fields in the object picked up by basic parsing.
+    /// The strict mode checks the fields after the matched one, and falls back to basic parsing if any of them has the same name.
+    #[inline]
+    pub fn set_strict(&mut self, strict: bool) {
+        self.parser.set_strict(strict);
+    }
+
+    /// Sets the limit of the nesting depth of the objects and arrays indexed for each record, which is 128 by default.
+    ///
+    /// The objects and arrays deeper than the queries are indexed only if there is a recursive descent, e.g. `$..id`,
+    /// and a record which has them nested deeper than this limit fails to be parsed with `ErrorKind::TooDeep`.
+    #[inline]
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.parser.index_builder.set_max_depth(max_depth);
+    }
+
+    /// Creates a JSON parser which starts from the training state exported by `Pikkr::export_stats`, and returns it.
+    #[inline]
+    pub fn with_stats<S: ?Sized + AsRef<[u8]>>(query_strs: &[&'a S], train_num: usize, stats: &[u8]) -> Result<Pikkr<'a>> {
+        let mut pikkr = Pikkr::new(query_strs, train_num)?;
+        pikkr.import_stats(stats)?;
+        Ok(pikkr)
+    }
+
+    /// Exports the training state, i.e. the positions learned for the queries and the number of records used for training.
+    ///
+    /// The result is a compact binary which can be imported by a parser with the same queries.
+    #[inline]
+    pub fn export_stats(&self) -> Vec<u8> {
+        stats::encode(self.queries.fingerprint(), self.trained_num, self.parser.stats())
+    }
+
+    /// Imports the training state exported by `Pikkr::export_stats`, replacing the current one.
+    ///
+    /// This function will return an error if the state is malformed or was exported by a parser with different queries.
+    #[inline]
+    pub fn import_stats(&mut self, stats: &[u8]) -> Result<()> {
+        let (trained_num, stats) = stats::decode(stats, self.queries.fingerprint(), self.queries.num_nodes())?;
+        self.parser.set_stats(stats);
+        self.trained_num = trained_num;
+        self.trained = trained_num >= self.train_num;
+        self.speculated_num = 0;
+        self.missed_num = 0;
+        Ok(())
     }
 
     /// Parses a JSON record and returns the result.
+    ///
+    /// If a query matches more than one value, the first one is returned.
     #[inline]
     pub fn parse<'b, S: ?Sized + AsRef<[u8]>>(&mut self, rec: &'b S) -> Result<Vec<Option<&'b [u8]>>> {
-        let rec = rec.as_ref();
-        if rec.len() == 0 {
-            return Err(Error::from(ErrorKind::InvalidRecord));
+        let mut results = vec![None; self.queries.num_paths()];
+        self.collect(rec.as_ref(), &mut results[..])?;
+        Ok(results)
+    }
+
+    /// Parses a JSON record and stores the result into `out`, which can be reused across records to avoid allocating the result.
+    ///
+    /// If a query matches more than one value, the first one is stored.
+    ///
+    /// # Panics
+    /// This function will panic if the length of `out` is not the number of queries.
+    #[inline]
+    pub fn parse_into<'b, S: ?Sized + AsRef<[u8]>>(&mut self, rec: &'b S, out: &mut [Option<&'b [u8]>]) -> Result<()> {
+        assert_eq!(self.queries.num_paths(), out.len(), "The length of the result buffer does not match the number of queries");
+        Collector::clear(out);
+        self.collect(rec.as_ref(), out)
+    }
+
+    /// Splits a buffer of concatenated JSON records such as `{...}{...}` into the byte ranges of the records.
+    ///
+    /// The records can be separated by whitespaces, and have to be objects or arrays.
+    #[inline]
+    pub fn split_concatenated<S: ?Sized + AsRef<[u8]>>(&mut self, buf: &S) -> Result<Vec<Range<usize>>> {
+        let mut records = Vec::new();
+        self.parser.index_builder.split_records(buf.as_ref(), &mut records)?;
+        Ok(records)
+    }
+
+    /// Splits a buffer of concatenated JSON records such as `{...}{...}` and passes the result of each record to `f`.
+    ///
+    /// This function will return an error without parsing any records if the buffer cannot be split into records.
+    #[inline]
+    pub fn parse_concatenated<'b, S, F>(&mut self, buf: &'b S, mut f: F) -> Result<()>
+    where
+        S: ?Sized + AsRef<[u8]>,
+        F: FnMut(Result<&[Option<&'b [u8]>]>),
+    {
+        let buf = buf.as_ref();
+        let records = self.split_concatenated(buf)?;
+        let mut results = vec![None; self.queries.num_paths()];
+        for r in records {
+            let result = self.parse_into(&buf[r], &mut results);
+            f(result.map(|_| &results[..]));
         }
+        Ok(())
+    }
 
-        self.parser.index_builder.build_structural_indices(rec)?;
+    /// Returns a reader which parses each line of `reader` as a JSON record, i.e. NDJSON or JSON Lines.
+    #[inline]
+    pub fn records<R: BufRead>(&mut self, reader: R) -> Records<'_, 'a, R> {
+        Records::new(self, reader)
+    }
+
+    /// Returns the counters of the records parsed so far.
+    #[inline]
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            query_misses: self.queries.sum_by_path(self.parser.misses()),
+            ..self.metrics.clone()
+        }
+    }
+
+    /// Resets the counters returned by `Pikkr::metrics`.
+    #[inline]
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+        self.parser.reset_misses();
+    }
+
+    /// Returns the number of queries.
+    #[inline]
+    pub fn num_queries(&self) -> usize {
+        self.queries.num_paths()
+    }
+
+    /// Parses a JSON record and returns the byte range of the value in the record for each query.
+    ///
+    /// If a query matches more than one value, the range of the first one is returned.
+    #[inline]
+    pub fn parse_spans<S: ?Sized + AsRef<[u8]>>(&mut self, rec: &S) -> Result<Vec<Option<Range<usize>>>> {
+        let mut results = vec![None; self.queries.num_paths()];
+        self.collect(rec.as_ref(), &mut results[..])?;
+        Ok(results)
+    }
+
+    /// Parses a JSON record and returns all the values matched by each query in order of appearance.
+    #[inline]
+    pub fn parse_multi<'b, S: ?Sized + AsRef<[u8]>>(&mut self, rec: &'b S) -> Result<Vec<Vec<&'b [u8]>>> {
+        let mut results = vec![Vec::new(); self.queries.num_paths()];
+        self.collect(rec.as_ref(), &mut results[..])?;
+        // the parser picks up values from the end of the record
+        for r in &mut results {
+            r.reverse();
+            if self.queries.is_recursive() {
+                // the values nested in the ones matched by a recursive descent are not picked up in order
+                r.sort_by_key(|v| v.as_ptr());
+            }
+        }
+        Ok(results)
+    }
+
+    /// Parses JSON records on `num_threads` threads and returns the result of each record.
+    ///
+    /// The records which are still needed for training are parsed first, and the positions learned by the threads are merged.
+    /// The other records are parsed speculatively by sharing the learned positions among the threads.
+    #[allow(clippy::type_complexity)]
+    pub fn parse_batch<'b, S: ?Sized + AsRef<[u8]> + Sync>(&mut self, recs: &[&'b S], num_threads: usize) -> Vec<Result<Vec<Option<&'b [u8]>>>> {
+        let mut results = Vec::with_capacity(recs.len());
+        let train_num = if self.trained {
+            0
+        } else {
+            cmp::min(self.train_num - self.trained_num, recs.len())
+        };
+
+        self.metrics.bytes_indexed += recs.iter().map(|r| (*r).as_ref().len() as u64).sum::<u64>();
+        self.parse_parallel(&recs[..train_num], num_threads, true, &mut results);
+        self.trained_num += results.iter().filter(|r| r.is_ok()).count();
+        if self.trained_num >= self.train_num {
+            self.trained = true;
+        }
+        let (missed_num, basic_num) = self.parse_parallel(&recs[train_num..], num_threads, false, &mut results);
+        let speculated_num = results[train_num..].iter().filter(|r| r.is_ok()).count() - basic_num;
+        self.count_speculation(speculated_num, missed_num);
+        self.metrics.records += results.iter().filter(|r| r.is_ok()).count() as u64;
+        self.metrics.speculative_hits += (speculated_num - missed_num) as u64;
+        self.metrics.fallbacks += missed_num as u64;
+        self.metrics.basic_parses += basic_num as u64;
+        results
+    }
+
+    /// Parses `recs` on `num_threads` threads, and returns the numbers of records whose speculation failed and which were partly parsed by basic parsing.
+    #[allow(clippy::type_complexity)]
+    fn parse_parallel<'b, S: ?Sized + AsRef<[u8]> + Sync>(&mut self, recs: &[&'b S], num_threads: usize, train: bool, results: &mut Vec<Result<Vec<Option<&'b [u8]>>>>) -> (usize, usize) {
+        if recs.is_empty() {
+            return (0, 0);
+        }
+        let num_threads = cmp::min(cmp::max(num_threads, 1), recs.len());
+        let chunk_size = recs.len().div_ceil(num_threads);
+        let queries = &self.queries;
+        let num_paths = queries.num_paths();
+
+        let mut parsers = (0..num_threads).map(|_| self.parser.fork(queries)).collect::<Vec<_>>();
+        let chunks = thread::scope(|scope| {
+            let handles = recs.chunks(chunk_size)
+                .zip(parsers.iter_mut())
+                .map(|(chunk, parser)| {
+                    scope.spawn(move || {
+                        let mut missed_num = 0;
+                        let mut basic_num = 0;
+                        let results = chunk
+                            .iter()
+                            .map(|rec| {
+                                let mut r = vec![None; num_paths];
+                                parser.parse((*rec).as_ref(), queries, train, &mut r[..]).map(|speculation| {
+                                    match speculation {
+                                        Speculation::Hit => {}
+                                        Speculation::Basic => basic_num += 1,
+                                        Speculation::Miss => missed_num += 1,
+                                    }
+                                    r
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        (results, missed_num, basic_num)
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect::<Vec<_>>()
+        });
+        let mut missed_num = 0;
+        let mut basic_num = 0;
+        for (chunk, m, b) in chunks {
+            results.extend(chunk);
+            missed_num += m;
+            basic_num += b;
+        }
+
+        self.parser.merge(parsers);
+        (missed_num, basic_num)
+    }
+
+    /// Returns the structural index of the last parsed record.
+    #[inline]
+    pub(crate) fn index_builder(&self) -> &IndexBuilder {
+        &self.parser.index_builder
+    }
+
+    #[inline]
+    pub(crate) fn collect<'b, C: ?Sized + Collector<'b>>(&mut self, rec: &'b [u8], results: &mut C) -> Result<()> {
+        self.metrics.bytes_indexed += rec.len() as u64;
+        let speculation = self.parser.parse(rec, &self.queries, !self.trained, results)?;
 
+        self.metrics.records += 1;
         if self.trained {
-            self.speculative_parse(rec)
+            match speculation {
+                Speculation::Hit => {
+                    self.metrics.speculative_hits += 1;
+                    self.count_speculation(1, 0);
+                }
+                Speculation::Miss => {
+                    self.metrics.fallbacks += 1;
+                    self.count_speculation(1, 1);
+                }
+                // a record which has no speculation to fail does not count toward retraining
+                Speculation::Basic => self.metrics.basic_parses += 1,
+            }
         } else {
-            let results = self.basic_parse(rec)?;
             self.trained_num += 1;
             if self.trained_num >= self.train_num {
                 self.trained = true;
             }
-            Ok(results)
         }
+        Ok(())
     }
 
-    fn speculative_parse<'b>(&mut self, rec: &'b [u8]) -> Result<Vec<Option<&'b [u8]>>> {
-        let mut results = vec![None; self.queries.num_paths()];
-        let found = self.parser
-            .speculative_parse(rec, &self.queries.as_node(), 0, rec.len() - 1, &mut results)?;
-        if !found {
-            self.parser.basic_parse(
-                rec,
-                &self.queries.as_node(),
-                0,
-                rec.len() - 1,
-                false,
-                &mut results,
-            )?;
+    /// Counts the records parsed speculatively, and starts retraining if too many of them have failed in the current window.
+    #[inline]
+    fn count_speculation(&mut self, speculated_num: usize, missed_num: usize) {
+        if self.retrain_window == 0 {
+            return;
+        }
+        self.speculated_num += speculated_num;
+        self.missed_num += missed_num;
+        if self.speculated_num >= self.retrain_window {
+            if self.missed_num as f64 > self.retrain_threshold * self.speculated_num as f64 {
+                self.trained = false;
+                self.trained_num = 0;
+            }
+            self.speculated_num = 0;
+            self.missed_num = 0;
         }
-        Ok(results)
-    }
-
-    fn basic_parse<'b>(&mut self, rec: &'b [u8]) -> Result<Vec<Option<&'b [u8]>>> {
-        let mut results = vec![None; self.queries.num_paths()];
-        self.parser.basic_parse(
-            rec,
-            &self.queries.as_node(),
-            0,
-            rec.len() - 1,
-            true,
-            &mut results,
-        )?;
-        Ok(results)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::error::{Error, ErrorKind};
 
     #[test]
     fn test_pikkr_new() {
@@ -300,4 +598,711 @@ mod tests {
             assert_eq!(t.want, got);
         }
     }
+
+    #[test]
+    fn test_pikkr_parse_array_index() {
+        let queries = vec![
+            "$.f1[0]".as_bytes(),
+            "$.f1[2].f1".as_bytes(),
+            "$.f2[1][0]".as_bytes(),
+            "$.f3".as_bytes(),
+        ];
+        struct TestCase<'a> {
+            rec: &'a str,
+            want: Result<Vec<Option<&'a [u8]>>>,
+        }
+        let test_cases = vec![
+            TestCase {
+                rec: r#"{"f1": ["a", "b", {"f1": "c"}], "f2": [[1], [2, 3]], "f3": {"f1": [4]}}"#,
+                want: Ok(vec![
+                    Some(r#""a""#.as_bytes()),
+                    Some(r#""c""#.as_bytes()),
+                    Some(r#"2"#.as_bytes()),
+                    Some(r#"{"f1": [4]}"#.as_bytes()),
+                ]),
+            },
+            TestCase {
+                rec: r#"{"f3": 0, "f2": [[1], [ 2 , 3 ]], "f1": [ "a" , {"f1": "b"} , {"f0": "c", "f1": "d"} ]}"#,
+                want: Ok(vec![
+                    Some(r#""a""#.as_bytes()),
+                    Some(r#""d""#.as_bytes()),
+                    Some(r#"2"#.as_bytes()),
+                    Some(r#"0"#.as_bytes()),
+                ]),
+            },
+            TestCase {
+                rec: r#"{"f1": [], "f2": [[1]], "f3": [{"f1": "a"}]}"#,
+                want: Ok(vec![
+                    None,
+                    None,
+                    None,
+                    Some(r#"[{"f1": "a"}]"#.as_bytes()),
+                ]),
+            },
+            TestCase {
+                rec: r#"{"f1": {"f1": "a"}, "f2": "[[1], [2]]"}"#,
+                want: Ok(vec![None, None, None, None]),
+            },
+        ];
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for t in &test_cases {
+                let got = p.parse(t.rec.as_bytes());
+                assert_eq!(t.want, got);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pikkr_parse_wildcard() {
+        let queries = vec![
+            "$.f1.*".as_bytes(),
+            "$.f2[*].id".as_bytes(),
+            "$.f2[*][*]".as_bytes(),
+            "$.f3".as_bytes(),
+        ];
+        struct TestCase<'a> {
+            rec: &'a str,
+            want: Result<Vec<Vec<&'a [u8]>>>,
+            want_first: Result<Vec<Option<&'a [u8]>>>,
+        }
+        let test_cases = vec![
+            TestCase {
+                rec: r#"{"f1": {"a": 1, "b": [2], "c": {"d": 3}}, "f2": [{"id": "x"}, {"no": "y"}, {"id": "z", "v": 0}], "f3": 4}"#,
+                want: Ok(vec![
+                    vec!["1".as_bytes(), "[2]".as_bytes(), r#"{"d": 3}"#.as_bytes()],
+                    vec![r#""x""#.as_bytes(), r#""z""#.as_bytes()],
+                    vec![r#""x""#.as_bytes(), r#""y""#.as_bytes(), r#""z""#.as_bytes(), "0".as_bytes()],
+                    vec!["4".as_bytes()],
+                ]),
+                want_first: Ok(vec![
+                    Some("1".as_bytes()),
+                    Some(r#""x""#.as_bytes()),
+                    Some(r#""x""#.as_bytes()),
+                    Some("4".as_bytes()),
+                ]),
+            },
+            TestCase {
+                rec: r#"{"f3": 5, "f2": [[1, 2], [], [3]], "f1": [6, 7]}"#,
+                want: Ok(vec![
+                    vec!["6".as_bytes(), "7".as_bytes()],
+                    vec![],
+                    vec!["1".as_bytes(), "2".as_bytes(), "3".as_bytes()],
+                    vec!["5".as_bytes()],
+                ]),
+                want_first: Ok(vec![
+                    Some("6".as_bytes()),
+                    None,
+                    Some("1".as_bytes()),
+                    Some("5".as_bytes()),
+                ]),
+            },
+            TestCase {
+                rec: r#"{"f1": {}, "f2": []}"#,
+                want: Ok(vec![vec![], vec![], vec![], vec![]]),
+                want_first: Ok(vec![None, None, None, None]),
+            },
+        ];
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for t in &test_cases {
+                let got = p.parse_multi(t.rec.as_bytes());
+                assert_eq!(t.want, got);
+                let got = p.parse(t.rec.as_bytes());
+                assert_eq!(t.want_first, got);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pikkr_parse_quoted_field() {
+        let queries = vec![
+            "$['user.name']".as_bytes(),
+            "$.user.name".as_bytes(),
+            r#"$["a b"][0]['it\'s']"#.as_bytes(),
+        ];
+        let rec = r#"{"user": {"name": "y"}, "a b": [{"it's": 1}], "user.name": "x"}"#;
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for _ in 0..2 {
+                let got = p.parse(rec.as_bytes());
+                assert_eq!(
+                    Ok(vec![
+                        Some(r#""x""#.as_bytes()),
+                        Some(r#""y""#.as_bytes()),
+                        Some(r#"1"#.as_bytes()),
+                    ]),
+                    got
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pikkr_parse_equivalent_queries() {
+        let queries = vec!["$.a".as_bytes(), "$['a']".as_bytes(), r#"$["a"]"#.as_bytes(), "$.b[0]".as_bytes(), "$['b'][0]".as_bytes()];
+        let rec = r#"{"a": 1, "b": [2]}"#;
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for _ in 0..2 {
+                let got = p.parse(rec.as_bytes());
+                assert_eq!(Ok(vec![Some("1".as_bytes()), Some("1".as_bytes()), Some("1".as_bytes()), Some("2".as_bytes()), Some("2".as_bytes())]), got);
+            }
+            let got = p.parse_multi(rec.as_bytes());
+            assert_eq!(Ok(vec![vec!["1".as_bytes()], vec!["1".as_bytes()], vec!["1".as_bytes()], vec!["2".as_bytes()], vec!["2".as_bytes()]]), got);
+        }
+    }
+
+    #[test]
+    fn test_pikkr_parse_escaped_field() {
+        let queries = vec![
+            "$.f1".as_bytes(),
+            "$.café".as_bytes(),
+            r#"$['x"y']"#.as_bytes(),
+            "$.f2.f1".as_bytes(),
+        ];
+        let recs = vec![
+            r#"{"\u0066\u0031": "a", "caf\u00e9": 1, "x\"y": 2, "f2": {"f\u0031": 3}}"#,
+            r#"{"f2": {"\u0066\u0031": 3}, "x\"y": 2, "café": 1, "f1": "a"}"#,
+        ];
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for rec in &recs {
+                let got = p.parse(rec.as_bytes());
+                assert_eq!(
+                    Ok(vec![
+                        Some(r#""a""#.as_bytes()),
+                        Some(r#"1"#.as_bytes()),
+                        Some(r#"2"#.as_bytes()),
+                        Some(r#"3"#.as_bytes()),
+                    ]),
+                    got
+                );
+            }
+        }
+
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        let got = p.parse(r#"{"f\u00": 1, "f1": 2}"#.as_bytes());
+        assert_eq!(Ok(vec![Some(r#"2"#.as_bytes()), None, None, None]), got);
+    }
+
+    #[test]
+    fn test_pikkr_parse_root_array() {
+        let queries = vec![
+            "$[0].a".as_bytes(),
+            "$[*].a".as_bytes(),
+            "$[2]".as_bytes(),
+            "$.a".as_bytes(),
+        ];
+        struct TestCase<'a> {
+            rec: &'a str,
+            want: Result<Vec<Vec<&'a [u8]>>>,
+        }
+        let test_cases = vec![
+            TestCase {
+                rec: r#"[{"a": 1}, {"b": 2}, {"a": 3}]"#,
+                want: Ok(vec![
+                    vec!["1".as_bytes()],
+                    vec!["1".as_bytes(), "3".as_bytes()],
+                    vec![r#"{"a": 3}"#.as_bytes()],
+                    vec![],
+                ]),
+            },
+            TestCase {
+                rec: r#"
+                    [ {"b": 0, "a": [4]} , 5 ]
+                "#,
+                want: Ok(vec![
+                    vec!["[4]".as_bytes()],
+                    vec!["[4]".as_bytes()],
+                    vec![],
+                    vec![],
+                ]),
+            },
+            TestCase {
+                rec: r#"[]"#,
+                want: Ok(vec![vec![], vec![], vec![], vec![]]),
+            },
+            TestCase {
+                rec: r#"{"a": [{"a": 6}]}"#,
+                want: Ok(vec![vec![], vec![], vec![], vec![r#"[{"a": 6}]"#.as_bytes()]]),
+            },
+        ];
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for t in &test_cases {
+                let got = p.parse_multi(t.rec.as_bytes());
+                assert_eq!(t.want, got);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pikkr_parse_recursive_descent() {
+        let queries = vec![
+            "$..id".as_bytes(),
+            "$.a..b.c".as_bytes(),
+            "$..[1]".as_bytes(),
+            "$.x".as_bytes(),
+        ];
+        struct TestCase<'a> {
+            rec: &'a str,
+            want: Result<Vec<Vec<&'a [u8]>>>,
+            want_first: Result<Vec<Option<&'a [u8]>>>,
+        }
+        let test_cases = vec![
+            TestCase {
+                rec: r#"{"id": 1, "a": {"b": {"c": 2, "id": 3}, "d": [{"b": {"x": {"b": {"c": 4}}}}, {"id": 5}]}, "x": [6, {"id": 7}]}"#,
+                want: Ok(vec![
+                    vec!["1".as_bytes(), "3".as_bytes(), "5".as_bytes(), "7".as_bytes()],
+                    vec!["2".as_bytes(), "4".as_bytes()],
+                    vec![r#"{"id": 5}"#.as_bytes(), r#"{"id": 7}"#.as_bytes()],
+                    vec![r#"[6, {"id": 7}]"#.as_bytes()],
+                ]),
+                want_first: Ok(vec![
+                    Some("1".as_bytes()),
+                    Some("2".as_bytes()),
+                    Some(r#"{"id": 5}"#.as_bytes()),
+                    Some(r#"[6, {"id": 7}]"#.as_bytes()),
+                ]),
+            },
+            TestCase {
+                rec: r#"{"a": {"x": {"a": {"b": {"c": 8}}}, "b": {"c": 9}}, "x": 0}"#,
+                want: Ok(vec![
+                    vec![],
+                    vec!["8".as_bytes(), "9".as_bytes()],
+                    vec![],
+                    vec!["0".as_bytes()],
+                ]),
+                want_first: Ok(vec![None, Some("8".as_bytes()), None, Some("0".as_bytes())]),
+            },
+            TestCase {
+                rec: r#"[[[[[[[[{"id": [10, 11]}]]]]]]]]"#,
+                want: Ok(vec![vec!["[10, 11]".as_bytes()], vec![], vec!["11".as_bytes()], vec![]]),
+                want_first: Ok(vec![Some("[10, 11]".as_bytes()), None, Some("11".as_bytes()), None]),
+            },
+            TestCase {
+                rec: r#"{"id": 12, "a": {"id": 13, "b": {"id": 14}, "id": 15}, "id": 16}"#,
+                want: Ok(vec![vec!["14".as_bytes(), "15".as_bytes(), "16".as_bytes()], vec![], vec![], vec![]]),
+                want_first: Ok(vec![Some("14".as_bytes()), None, None, None]),
+            },
+            TestCase {
+                rec: r#"{"x": "id"}"#,
+                want: Ok(vec![vec![], vec![], vec![], vec![r#""id""#.as_bytes()]]),
+                want_first: Ok(vec![None, None, None, Some(r#""id""#.as_bytes())]),
+            },
+        ];
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for t in &test_cases {
+                let got = p.parse_multi(t.rec.as_bytes());
+                assert_eq!(t.want, got);
+                let got = p.parse(t.rec.as_bytes());
+                assert_eq!(t.want_first, got);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pikkr_max_depth() {
+        let queries = vec!["$..id".as_bytes()];
+        let deep = format!(r#"{}{{"id": 1}}{}"#, "[".repeat(300), "]".repeat(300));
+        let shallow = r#"{"a": {"id": 2}}"#;
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        assert_eq!(Err(Error::from(ErrorKind::TooDeep)), p.parse(&deep));
+        assert_eq!(Ok(vec![Some("2".as_bytes())]), p.parse(shallow));
+
+        p.set_max_depth(301);
+        assert_eq!(Ok(vec![Some("1".as_bytes())]), p.parse(&deep));
+        assert_eq!(Ok(vec![Some("2".as_bytes())]), p.parse(shallow));
+        assert_eq!(2, p.parser.index_builder.index.len());
+    }
+
+    #[test]
+    fn test_pikkr_parse_spans() {
+        let queries = vec![
+            "$.a".as_bytes(),
+            "$.b[1]".as_bytes(),
+            "$.b[*].c".as_bytes(),
+            "$.d".as_bytes(),
+        ];
+        struct TestCase<'a> {
+            rec: &'a str,
+            want: Result<Vec<Option<Range<usize>>>>,
+        }
+        let test_cases = vec![
+            TestCase {
+                rec: r#"{"a": "x", "b": [{"c": 1}, {"c": 23}]}"#,
+                want: Ok(vec![Some(6..9), Some(27..36), Some(23..24), None]),
+            },
+            TestCase {
+                rec: r#"{ "b" : [ 4 ] , "a" : [ ] }"#,
+                want: Ok(vec![Some(22..25), None, None, None]),
+            },
+            TestCase {
+                rec: r#"{"a": 1}}"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+        ];
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for t in &test_cases {
+                let got = p.parse_spans(t.rec.as_bytes());
+                assert_eq!(t.want, got);
+                if let Ok(spans) = got {
+                    let values = p.parse(t.rec.as_bytes()).unwrap();
+                    let want = spans.into_iter().map(|r| r.map(|r| &t.rec.as_bytes()[r])).collect::<Vec<_>>();
+                    assert_eq!(want, values);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pikkr_parse_into() {
+        let queries = vec!["$.a".as_bytes(), "$.b.c".as_bytes()];
+        let recs = vec![
+            r#"{"a": 1, "b": {"c": 2}}"#,
+            r#"{"b": {"c": 3}}"#,
+            r#"{"b": {"d": 4}, "a": 5}"#,
+            r#"{"a": 6}}"#,
+            r#"{}"#,
+        ];
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            let mut q = Pikkr::new(&queries, train_num).unwrap();
+            let mut out = vec![None; p.num_queries()];
+            for rec in &recs {
+                let got = p.parse_into(rec, &mut out).map(|_| out.clone());
+                assert_eq!(q.parse(rec), got);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pikkr_parse_into_short_buffer() {
+        let queries = vec!["$.a".as_bytes(), "$.b".as_bytes()];
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        let mut out = vec![None; 1];
+        let _ = p.parse_into(r#"{"a": 1}"#, &mut out);
+    }
+
+    #[test]
+    fn test_pikkr_parse_concatenated() {
+        let queries = vec!["$.a".as_bytes(), "$[0]".as_bytes()];
+        struct TestCase<'a> {
+            buf: &'a str,
+            want: Result<Vec<Result<Vec<Option<&'a [u8]>>>>>,
+        }
+        let test_cases = vec![
+            TestCase {
+                buf: r#"{"a": 1}{"a": "}{"}  [2, 3]{"a": {"b": [4]}}"#,
+                want: Ok(vec![
+                    Ok(vec![Some("1".as_bytes()), None]),
+                    Ok(vec![Some(r#""}{""#.as_bytes()), None]),
+                    Ok(vec![None, Some("2".as_bytes())]),
+                    Ok(vec![Some(r#"{"b": [4]}"#.as_bytes()), None]),
+                ]),
+            },
+            TestCase {
+                buf: "1",
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                buf: "",
+                want: Ok(vec![]),
+            },
+            TestCase {
+                buf: r#"{"a": 1} {"a": 2"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+        ];
+        for train_num in vec![1, 1000000000] {
+            let mut p = Pikkr::new(&queries, train_num).unwrap();
+            for t in &test_cases {
+                let mut results = Vec::new();
+                let got = p.parse_concatenated(t.buf, |r| results.push(r.map(|r| r.to_vec()))).map(|_| results);
+                assert_eq!(t.want, got);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pikkr_parse_batch() {
+        let queries = vec!["$.a".as_bytes(), "$.b.c".as_bytes(), "$.d[*]".as_bytes()];
+        let mut recs = Vec::new();
+        for i in 0..50 {
+            recs.push(match i % 5 {
+                0 => format!(r#"{{"a": {}, "b": {{"c": "x"}}, "d": [1, 2]}}"#, i),
+                1 => format!(r#"{{"b": {{"c": {}}}, "a": null}}"#, i),
+                2 => format!(r#"{{"e": 0, "d": [], "a": [{}]}}"#, i),
+                3 => String::from(r#"{"a": }}"#),
+                _ => format!(r#"{{"d": [{}], "b": {{"x": 1, "c": true}}}}"#, i),
+            });
+        }
+        let recs = recs.iter().map(|r| r.as_str()).collect::<Vec<_>>();
+        for train_num in vec![0, 1, 7, 1000000000] {
+            let mut q = Pikkr::new(&queries, train_num).unwrap();
+            let want = recs.iter().map(|r| q.parse(r)).collect::<Vec<_>>();
+            for num_threads in vec![0, 1, 3, 8, 100] {
+                let mut p = Pikkr::new(&queries, train_num).unwrap();
+                let mut got = p.parse_batch(&recs[..10], num_threads);
+                got.extend(p.parse_batch(&recs[10..], num_threads));
+                assert_eq!(want, got);
+                assert_eq!(q.trained, p.trained);
+                assert!(p.parse_batch::<str>(&[], num_threads).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_pikkr_clone() {
+        fn assert_send<T: Send + Clone>(_: &T) {}
+
+        let queries = vec!["$.a".as_bytes(), "$.b".as_bytes()];
+        let recs = [r#"{"a": 1, "b": 2}"#, r#"{"b": 3, "a": 4}"#, r#"{"a": 5}"#];
+        let parse = move |p: &mut Pikkr| {
+            recs.iter()
+                .map(|rec| p.parse(rec).map(|r| r.iter().map(|v| v.map(|v| v.to_vec())).collect::<Vec<_>>()))
+                .collect::<Vec<_>>()
+        };
+        let mut p = Pikkr::new(&queries, 2).unwrap();
+        assert_send(&p);
+        p.parse(recs[0]).unwrap();
+        let mut q = p.clone();
+        assert_eq!(p.trained_num, q.trained_num);
+        p.parse(recs[1]).unwrap();
+        assert!(p.trained);
+        assert!(!q.trained);
+
+        let mut r = p.clone();
+        let handle = thread::spawn(move || {
+            assert!(r.trained);
+            parse(&mut r)
+        });
+        assert_eq!(parse(&mut q), handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_pikkr_new_owned() {
+        fn new_static(query_strs: Vec<String>) -> Result<Pikkr<'static>> {
+            Pikkr::new_owned(query_strs, 1)
+        }
+        let query_strs = vec!["$.a", "$['b.c'][0]", "$..d", "$.a"];
+        let mut p = new_static(query_strs.iter().map(|s| s.to_string()).collect()).unwrap();
+        let mut q = Pikkr::new(&query_strs, 1).unwrap();
+        let recs = vec![r#"{"a": 1, "b.c": [2], "x": {"d": 3}}"#, r#"{"d": 4, "b.c": [], "a": 5}"#];
+        for rec in recs {
+            assert_eq!(q.parse(rec), p.parse(rec));
+        }
+
+        assert!(Pikkr::new_owned(vec![b"$.a".to_vec()], 1).is_ok());
+        assert_eq!(
+            Some(ErrorKind::InvalidQuery),
+            new_static(vec![String::from("$.a"), String::from("$.")]).err().map(|e| e.kind())
+        );
+    }
+
+    #[test]
+    fn test_pikkr_export_stats() {
+        let queries = vec!["$.a".as_bytes(), "$.b.c".as_bytes()];
+        let recs = vec![r#"{"x": 0, "a": 1, "b": {"c": 2}}"#, r#"{"b": {"d": 3, "c": 4}, "a": 5}"#];
+        let mut p = Pikkr::new(&queries, 2).unwrap();
+        for rec in &recs {
+            p.parse(rec).unwrap();
+        }
+        let stats = p.export_stats();
+
+        let mut q = Pikkr::with_stats(&queries, 2, &stats).unwrap();
+        assert!(q.trained);
+        assert_eq!(p.parser.stats(), q.parser.stats());
+        assert_eq!(stats, q.export_stats());
+        for rec in &recs {
+            assert_eq!(p.parse(rec), q.parse(rec));
+        }
+
+        let q = Pikkr::with_stats(&queries, 3, &stats).unwrap();
+        assert!(!q.trained);
+        assert_eq!(2, q.trained_num);
+
+        let mut q = Pikkr::new_owned(vec!["$.a", "$.b.c"], 2).unwrap();
+        assert_eq!(Ok(()), q.import_stats(&stats));
+        assert!(q.trained);
+
+        let invalid = Some(ErrorKind::InvalidStats);
+        assert_eq!(invalid, Pikkr::with_stats(&["$.b.c", "$.a"], 2, &stats).err().map(|e| e.kind()));
+        assert_eq!(invalid, Pikkr::with_stats(&["$.a", "$.b.c", "$.d"], 2, &stats).err().map(|e| e.kind()));
+        assert_eq!(invalid, Pikkr::with_stats(&queries, 2, &stats[1..]).err().map(|e| e.kind()));
+    }
+
+    #[test]
+    fn test_pikkr_candidate_ranking() {
+        let queries = vec!["$.a".as_bytes()];
+        let rare = r#"{"a": 1}"#;
+        let common = r#"{"x": 0, "a": 2}"#;
+        let mut p = Pikkr::new(&queries, 3).unwrap();
+        for rec in &[rare, common, common] {
+            p.parse(rec).unwrap();
+        }
+        let id = p.queries.as_node().get_child(b"a").unwrap().id();
+        assert_eq!(vec![(1, 2), (0, 1)], p.parser.stats()[id].iter().collect::<Vec<_>>());
+
+        // the counts are not updated by speculative parsing unless online learning is enabled
+        assert_eq!(Ok(vec![Some("1".as_bytes())]), p.parse(rare));
+        assert_eq!(vec![(1, 2), (0, 1)], p.parser.stats()[id].iter().collect::<Vec<_>>());
+
+        p.set_online_learning(true);
+        for _ in 0..2 {
+            assert_eq!(Ok(vec![Some("1".as_bytes())]), p.parse(rare));
+        }
+        assert_eq!(vec![(0, 3), (1, 2)], p.parser.stats()[id].iter().collect::<Vec<_>>());
+
+        let results = p.parse_batch(&[rare, rare, common, rare], 3);
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(vec![(0, 6), (1, 3)], p.parser.stats()[id].iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_pikkr_duplicate_fields() {
+        let queries = vec!["$.a".as_bytes(), "$.b".as_bytes()];
+        let train_rec = r#"{"a": 1, "b": 2}"#;
+        let rec = r#"{"a": 3, "b": 4, "a": 5}"#;
+        let want = Ok(vec![Some("5".as_bytes()), Some("4".as_bytes())]);
+
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        p.parse(train_rec).unwrap();
+        assert_eq!(Ok(vec![Some("3".as_bytes()), Some("4".as_bytes())]), p.parse(rec));
+        p.set_strict(true);
+        assert_eq!(want, p.parse(rec));
+        assert_eq!(1, p.metrics().fallbacks);
+
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        assert_eq!(want, p.parse(rec));
+    }
+
+    #[test]
+    fn test_pikkr_retraining() {
+        let queries = vec!["$.a".as_bytes(), "$.b".as_bytes()];
+        let old_rec = r#"{"a": 1, "b": 2}"#;
+        let new_rec = r#"{"x": 0, "b": 3, "a": 4}"#;
+
+        let mut p = Pikkr::new(&queries, 2).unwrap();
+        p.set_retraining(4, 0.5);
+        for _ in 0..2 {
+            p.parse(old_rec).unwrap();
+        }
+        assert!(p.trained);
+        for _ in 0..3 {
+            assert_eq!(Ok(vec![Some("4".as_bytes()), Some("3".as_bytes())]), p.parse(new_rec));
+            assert!(p.trained);
+        }
+        // the 4th failure in the window of 4 records exceeds the threshold
+        p.parse(new_rec).unwrap();
+        assert!(!p.trained);
+        assert_eq!(0, p.trained_num);
+        for _ in 0..2 {
+            p.parse(new_rec).unwrap();
+        }
+        assert!(p.trained);
+        for rec in &[old_rec, new_rec] {
+            let mut results: Vec<Option<&[u8]>> = vec![None; 2];
+            assert_eq!(Ok(Speculation::Hit), p.parser.parse(rec.as_bytes(), &p.queries, false, &mut results[..]));
+        }
+
+        // misses within the threshold do not start retraining
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        p.set_retraining(4, 0.5);
+        p.parse(old_rec).unwrap();
+        for rec in &[new_rec, old_rec, new_rec, old_rec] {
+            p.parse(rec).unwrap();
+        }
+        assert!(p.trained);
+
+        // records parsed in batches are counted as well
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        p.set_retraining(4, 0.5);
+        p.parse_batch(&[old_rec, new_rec, new_rec, new_rec], 2);
+        assert!(p.trained);
+        p.parse_batch(&[new_rec], 2);
+        assert!(!p.trained);
+
+        // retraining is disabled by default
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        p.parse(old_rec).unwrap();
+        for _ in 0..100 {
+            p.parse(new_rec).unwrap();
+        }
+        assert!(p.trained);
+    }
+
+    #[test]
+    fn test_pikkr_metrics() {
+        let queries = vec!["$.a.b".as_bytes(), "$.a.c".as_bytes(), "$.d".as_bytes()];
+        let recs = vec![
+            r#"{"a": {"b": 1, "c": 2}, "d": 3}"#,
+            r#"{"a": {"b": 4, "c": 5}, "d": 6}"#,
+            r#"{"a": {"b": 7, "c": 8}, "e": 0, "d": 9}"#,
+            r#"{"a": {"b": 10, "e": 0, "c": 11}, "d": 12}"#,
+            r#"{"e": 0, "a": {"b": 13, "c": 14}, "d": 15}"#,
+            r#"{"a": }}"#,
+        ];
+        let bytes = recs.iter().map(|r| r.len() as u64).sum::<u64>();
+        let want = Metrics {
+            records: 5,
+            speculative_hits: 1,
+            fallbacks: 3,
+            basic_parses: 0,
+            query_misses: vec![1, 2, 2],
+            bytes_indexed: bytes,
+        };
+
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        assert_eq!(Metrics { query_misses: vec![0; 3], ..Metrics::default() }, p.metrics());
+        for rec in &recs {
+            let _ = p.parse(rec);
+        }
+        assert_eq!(want, p.metrics());
+        p.reset_metrics();
+        assert_eq!(Metrics { query_misses: vec![0; 3], ..Metrics::default() }, p.metrics());
+
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        p.parse_batch(&recs, 3);
+        assert_eq!(want, p.metrics());
+    }
+
+    #[test]
+    fn test_pikkr_metrics_wildcard() {
+        let queries = vec!["$.a.*".as_bytes(), "$.b".as_bytes()];
+        let recs = vec![
+            r#"{"a": {"x": 1}, "b": 2}"#,
+            r#"{"a": {"x": 3, "y": 4}, "b": 5}"#,
+            r#"{"b": 6, "a": [7]}"#,
+            r#"{"a": {}, "b": 8}"#,
+            r#"{"a": {"z": 9}, "b": 10}"#,
+        ];
+        let want = Metrics {
+            records: 5,
+            speculative_hits: 0,
+            fallbacks: 1,
+            basic_parses: 3,
+            query_misses: vec![1, 1],
+            bytes_indexed: recs.iter().map(|r| r.len() as u64).sum::<u64>(),
+        };
+
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        p.set_retraining(2, 0.4);
+        for rec in &recs {
+            p.parse(rec).unwrap();
+        }
+        assert_eq!(want, p.metrics());
+        // the records parsed by basic parsing do not count toward retraining
+        assert!(p.trained);
+
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        p.set_retraining(2, 0.4);
+        p.parse_batch(&recs, 2);
+        assert_eq!(want, p.metrics());
+        assert!(p.trained);
+    }
 }
@@ -1,34 +1,45 @@
+use std::borrow::Cow;
 use std::cmp;
 use std::collections::hash_map;
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHasher};
+use std::hash::Hasher;
 use error::{Error, ErrorKind};
 use result::Result;
-use utf8::{DOLLAR, DOT};
+use utf8::{APOSTROPHE, ASTERISK, BACKSLASH, DOLLAR, DOT, LEFT_BRACKET, QUOTE, RIGHT_BRACKET};
 
 const ROOT_QUERY_STR_OFFSET: usize = 2;
 
 
 /// A node in pattern tree
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct QueryNode<'a> {
     /// The identifier of this node
     node_id: Option<usize>,
 
-    /// A identifier of path associated with this node
-    path_id: Option<usize>,
+    /// The identifiers of paths associated with this node, which are more than one if the same path is queried in different spellings
+    path_ids: Vec<usize>,
 
     /// Level of this node in the pattern tree
     level: usize,
 
     /// Children of this node
-    children: FnvHashMap<&'a [u8], QueryNode<'a>>,
+    children: FnvHashMap<Cow<'a, [u8]>, QueryNode<'a>>,
+
+    /// Children of this node which are associated with array elements
+    elements: FnvHashMap<usize, QueryNode<'a>>,
+
+    /// A child of this node which is associated with all the members or elements
+    wildcard: Option<Box<QueryNode<'a>>>,
+
+    /// A virtual child of this node whose children are matched at any depth below this node
+    descendant: Option<Box<QueryNode<'a>>>,
 }
 
 impl<'a> QueryNode<'a> {
     /// Returns whether this node is a leaf or not.
     #[inline]
     pub fn is_leaf(&self) -> bool {
-        self.children.is_empty()
+        self.children.is_empty() && self.elements.is_empty() && self.wildcard.is_none() && self.descendant.is_none()
     }
 
     /// Returns the identifier of this node, if avaialble.
@@ -48,12 +59,12 @@ impl<'a> QueryNode<'a> {
         self.node_id.expect("The node is a root")
     }
 
-    /// Returns the path identifier associated with this node.
+    /// Returns the path identifiers associated with this node.
     ///
-    /// This function will return a `None` if the node is not a target.
+    /// This function will return an empty slice if the node is not a target.
     #[inline]
-    pub fn path_id(&self) -> Option<usize> {
-        self.path_id
+    pub fn path_ids(&self) -> &[usize] {
+        &self.path_ids
     }
 
     /// Returns the level of this node.
@@ -68,7 +79,7 @@ impl<'a> QueryNode<'a> {
         self.children.get(field)
     }
 
-    /// Returns the number of childrens of this node.
+    /// Returns the number of childrens of this node, excluding the ones associated with array elements.
     ///
     /// This function will return a zero if it is a leaf.
     #[inline]
@@ -77,19 +88,49 @@ impl<'a> QueryNode<'a> {
     }
 
     #[inline]
-    pub fn iter(&self) -> hash_map::Iter<&'a [u8], QueryNode<'a>> {
+    pub fn iter(&self) -> hash_map::Iter<'_, Cow<'a, [u8]>, QueryNode<'a>> {
         self.children.iter()
     }
+
+    /// Returns the reference of a child associated with the `i`-th array element, if available.
+    #[inline]
+    pub fn get_element(&self, i: usize) -> Option<&QueryNode<'a>> {
+        self.elements.get(&i)
+    }
+
+    /// Returns the number of childrens of this node which are associated with array elements.
+    #[inline]
+    pub fn num_elements(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns the reference of a child associated with all the members or elements, if available.
+    #[inline]
+    pub fn wildcard(&self) -> Option<&QueryNode<'a>> {
+        self.wildcard.as_deref()
+    }
+
+    /// Returns the reference of a virtual child whose children are matched at any depth below this node, if available.
+    #[inline]
+    pub fn descendant(&self) -> Option<&QueryNode<'a>> {
+        self.descendant.as_deref()
+    }
+
+    #[inline]
+    pub fn iter_elements(&self) -> hash_map::Iter<'_, usize, QueryNode<'a>> {
+        self.elements.iter()
+    }
 }
 
 
 /// A pattern tree associated with the queries
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct QueryTree<'a> {
     root_node: QueryNode<'a>,
-    paths: Vec<&'a [u8]>,
+    paths: Vec<Cow<'a, [u8]>>,
     max_level: usize,
     num_nodes: usize,
+    recursive: bool,
 }
 
 impl<'a> QueryTree<'a> {
@@ -97,23 +138,35 @@ impl<'a> QueryTree<'a> {
     pub fn new<S: ?Sized + AsRef<[u8]>>(paths: &[&'a S]) -> Result<Self> {
         let mut tree = Self::default();
         for path in paths {
-            tree.add_path((*path).as_ref())?;
+            let path = (*path).as_ref();
+            let steps = parse_query_str(path).ok_or_else(|| Error::from(ErrorKind::InvalidQuery))?;
+            tree.add_path(Cow::Borrowed(path), steps);
         }
         Ok(tree)
     }
 
-    /// Add a path into the pattern tree.
-    fn add_path(&mut self, path: &'a [u8]) -> Result<()> {
-        if !is_valid_query_str(path) {
-            return Err(Error::from(ErrorKind::InvalidQuery));
+    /// Create a new instance of `QueryTree` which owns the given path sequence.
+    pub fn new_owned<S: Into<Vec<u8>>, I: IntoIterator<Item = S>>(paths: I) -> Result<QueryTree<'static>> {
+        let mut tree = QueryTree::default();
+        for path in paths {
+            let path = path.into();
+            let steps = parse_query_str(&path)
+                .ok_or_else(|| Error::from(ErrorKind::InvalidQuery))?
+                .into_iter()
+                .map(Step::into_owned)
+                .collect();
+            tree.add_path(Cow::Owned(path), steps);
         }
+        Ok(tree)
+    }
 
+    /// Add a path into the pattern tree.
+    fn add_path(&mut self, path: Cow<'a, [u8]>, steps: Vec<Step<'a>>) {
         let mut cur = &mut self.root_node;
-        for field in path[ROOT_QUERY_STR_OFFSET..].split(|&b| b == DOT) {
+        for step in steps {
             let level = cur.level + 1;
             let num_nodes = &mut self.num_nodes;
-            let cur1 = cur; // workaround for lifetime error
-            cur = cur1.children.entry(field).or_insert_with(|| {
+            let mut new_node = || {
                 let node = QueryNode {
                     node_id: Some(*num_nodes),
                     level,
@@ -121,15 +174,23 @@ impl<'a> QueryTree<'a> {
                 };
                 *num_nodes += 1;
                 node
-            });
+            };
+            let cur1 = cur; // workaround for lifetime error
+            cur = match step {
+                Step::Field(field) => cur1.children.entry(field).or_insert_with(new_node),
+                Step::Index(i) => cur1.elements.entry(i).or_insert_with(new_node),
+                Step::Wildcard => cur1.wildcard.get_or_insert_with(|| Box::new(new_node())),
+                Step::Descendant => {
+                    self.recursive = true;
+                    cur1.descendant.get_or_insert_with(|| Box::new(new_node()))
+                }
+            };
         }
         // mark the last node as a target
-        cur.path_id = Some(self.paths.len());
+        cur.path_ids.push(self.paths.len());
 
         self.max_level = cmp::max(self.max_level, cur.level);
         self.paths.push(path);
-
-        Ok(())
     }
 
     /// Returns the reference of root node of this pattern tree.
@@ -144,6 +205,43 @@ impl<'a> QueryTree<'a> {
         self.max_level
     }
 
+    /// Returns whether this pattern tree contains a recursive descent, whose values may be nested at any level.
+    #[inline]
+    pub fn is_recursive(&self) -> bool {
+        self.recursive
+    }
+
+    /// Sums up the values of the nodes on the path from the root for each query path.
+    ///
+    /// `values` is indexed by the node identifiers.
+    pub fn sum_by_path(&self, values: &[u64]) -> Vec<u64> {
+        fn walk(node: &QueryNode, sum: u64, values: &[u64], sums: &mut [u64]) {
+            let sum = sum + node.node_id.map_or(0, |i| values[i]);
+            for &i in &node.path_ids {
+                sums[i] = sum;
+            }
+            for child in node.children.values().chain(node.elements.values()) {
+                walk(child, sum, values, sums);
+            }
+            for child in node.wildcard.iter().chain(node.descendant.iter()) {
+                walk(child, sum, values, sums);
+            }
+        }
+        let mut sums = vec![0; self.num_paths()];
+        walk(&self.root_node, 0, values, &mut sums);
+        sums
+    }
+
+    /// Returns the fingerprint of the query paths, which identifies the structure of this pattern tree.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        for path in &self.paths {
+            hasher.write(&(path.len() as u64).to_le_bytes());
+            hasher.write(path);
+        }
+        hasher.finish()
+    }
+
     /// Returns the number of query paths, registered in this pattern tree.
     #[inline]
     pub fn num_paths(&self) -> usize {
@@ -157,86 +255,375 @@ impl<'a> QueryTree<'a> {
     }
 }
 
+/// A step of a query path
+#[derive(Debug, PartialEq)]
+enum Step<'a> {
+    /// A field of an object, i.e. `.field` or `['field']`
+    Field(Cow<'a, [u8]>),
+
+    /// An element of an array, i.e. `[i]`
+    Index(usize),
+
+    /// All the members of an object or all the elements of an array, i.e. `.*` or `[*]`
+    Wildcard,
+
+    /// All the values nested at any depth, followed by the step matched against them, i.e. `..`
+    Descendant,
+}
+
+impl<'a> Step<'a> {
+    /// Converts this step into the one which owns the field name.
+    #[inline]
+    fn into_owned(self) -> Step<'static> {
+        match self {
+            Step::Field(field) => Step::Field(Cow::Owned(field.into_owned())),
+            Step::Index(i) => Step::Index(i),
+            Step::Wildcard => Step::Wildcard,
+            Step::Descendant => Step::Descendant,
+        }
+    }
+}
+
+/// Splits a query string into its steps.
+///
+/// This function will return a `None` if the query string is invalid.
 #[inline]
-fn is_valid_query_str(query_str: &[u8]) -> bool {
-    if query_str.len() < ROOT_QUERY_STR_OFFSET + 1 || query_str[0] != DOLLAR || query_str[1] != DOT {
-        return false;
-    }
-    let mut s = ROOT_QUERY_STR_OFFSET - 1;
-    for i in s + 1..query_str.len() {
-        if query_str[i] != DOT {
-            continue;
+fn parse_query_str<'a>(query_str: &'a [u8]) -> Option<Vec<Step<'a>>> {
+    if query_str.len() < ROOT_QUERY_STR_OFFSET + 1 || query_str[0] != DOLLAR || (query_str[1] != DOT && query_str[1] != LEFT_BRACKET) {
+        return None;
+    }
+    let n = query_str.len();
+    let mut steps = Vec::new();
+    let mut i = 1;
+    while i < n {
+        let s = i + 1;
+        let mut e = s;
+        match query_str[i] {
+            DOT if s < n && query_str[s] == DOT => {
+                // `..` is followed by a field, a wildcard or a bracket
+                match query_str.get(s + 1) {
+                    None | Some(&DOT) => return None,
+                    Some(&LEFT_BRACKET) => i = s + 1,
+                    Some(_) => i = s,
+                }
+                steps.push(Step::Descendant);
+            }
+            DOT => {
+                while e < n && query_str[e] != DOT && query_str[e] != LEFT_BRACKET {
+                    e += 1;
+                }
+                if e == s {
+                    return None;
+                }
+                if query_str[s..e] == [ASTERISK] {
+                    steps.push(Step::Wildcard);
+                } else {
+                    steps.push(Step::Field(Cow::Borrowed(&query_str[s..e])));
+                }
+                i = e;
+            }
+            LEFT_BRACKET if s < n && (query_str[s] == QUOTE || query_str[s] == APOSTROPHE) => {
+                let (field, e) = parse_quoted_field(query_str, s)?;
+                if e == n || query_str[e] != RIGHT_BRACKET {
+                    return None;
+                }
+                steps.push(Step::Field(field));
+                i = e + 1;
+            }
+            LEFT_BRACKET if query_str[s..].starts_with(&[ASTERISK, RIGHT_BRACKET]) => {
+                steps.push(Step::Wildcard);
+                i = s + 2;
+            }
+            LEFT_BRACKET => {
+                let mut index = 0usize;
+                while e < n && query_str[e].is_ascii_digit() {
+                    index = index.checked_mul(10)?.checked_add((query_str[e] - b'0') as usize)?;
+                    e += 1;
+                }
+                if e == s || e == n || query_str[e] != RIGHT_BRACKET {
+                    return None;
+                }
+                steps.push(Step::Index(index));
+                i = e + 1;
+            }
+            _ => {
+                return None;
+            }
         }
-        if i == s + 1 || i == query_str.len() - 1 {
-            return false;
+    }
+    Some(steps)
+}
+
+/// Parses a field name enclosed by the quote at `query_str[s]`, and returns it with the index next to the closing quote.
+///
+/// Only the quotes and backslash can be escaped by a backslash.
+#[inline]
+fn parse_quoted_field<'a>(query_str: &'a [u8], s: usize) -> Option<(Cow<'a, [u8]>, usize)> {
+    let quote = query_str[s];
+    let mut field: Option<Vec<u8>> = None;
+    let mut cs = s + 1;
+    let mut i = s + 1;
+    while i < query_str.len() {
+        match query_str[i] {
+            BACKSLASH => {
+                let c = *query_str.get(i + 1)?;
+                if c != BACKSLASH && c != QUOTE && c != APOSTROPHE {
+                    return None;
+                }
+                let f = field.get_or_insert_with(Vec::new);
+                f.extend_from_slice(&query_str[cs..i]);
+                f.push(c);
+                i += 2;
+                cs = i;
+            }
+            c if c == quote => {
+                let field = match field {
+                    Some(mut f) => {
+                        f.extend_from_slice(&query_str[cs..i]);
+                        Cow::Owned(f)
+                    }
+                    None => Cow::Borrowed(&query_str[cs..i]),
+                };
+                return Some((field, i + 1));
+            }
+            _ => {
+                i += 1;
+            }
         }
-        s = i;
     }
-    true
+    None
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn field(s: &str) -> Step<'_> {
+        Step::Field(Cow::Borrowed(s.as_bytes()))
+    }
+
     #[test]
-    fn test_is_valid_query_str() {
+    fn test_parse_query_str() {
         struct TestCase<'a> {
             query_str: &'a str,
-            want: bool,
+            want: Option<Vec<Step<'a>>>,
         }
         let test_cases = vec![
             TestCase {
                 query_str: "",
-                want: false,
+                want: None,
             },
             TestCase {
                 query_str: "$",
-                want: false,
+                want: None,
             },
             TestCase {
                 query_str: "$.",
-                want: false,
+                want: None,
             },
             TestCase {
                 query_str: "$..",
-                want: false,
+                want: None,
             },
             TestCase {
                 query_str: "a.a",
-                want: false,
+                want: None,
             },
             TestCase {
                 query_str: "$aa",
-                want: false,
+                want: None,
             },
             TestCase {
                 query_str: "$.a",
-                want: true,
+                want: Some(vec![field("a")]),
             },
             TestCase {
                 query_str: "$.aaaa",
-                want: true,
+                want: Some(vec![field("aaaa")]),
             },
             TestCase {
                 query_str: "$.aaaa.",
-                want: false,
+                want: None,
             },
             TestCase {
                 query_str: "$.aaaa.b",
-                want: true,
+                want: Some(vec![field("aaaa"), field("b")]),
             },
             TestCase {
                 query_str: "$.aaaa.bbbb",
-                want: true,
+                want: Some(vec![field("aaaa"), field("bbbb")]),
             },
             TestCase {
                 query_str: "$.aaaa.bbbb.",
-                want: false,
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a[3].b",
+                want: Some(vec![field("a"), Step::Index(3), field("b")]),
+            },
+            TestCase {
+                query_str: "$.a[0][12]",
+                want: Some(vec![field("a"), Step::Index(0), Step::Index(12)]),
+            },
+            TestCase {
+                query_str: "$.a[",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a[]",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a[1",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a[-1]",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a[b]",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a[1]b",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a.[1]",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a[99999999999999999999999]",
+                want: None,
+            },
+            TestCase {
+                query_str: "$[0]",
+                want: Some(vec![Step::Index(0)]),
+            },
+            TestCase {
+                query_str: "$[0].a[1]",
+                want: Some(vec![Step::Index(0), field("a"), Step::Index(1)]),
+            },
+            TestCase {
+                query_str: "$[",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.*",
+                want: Some(vec![Step::Wildcard]),
+            },
+            TestCase {
+                query_str: "$.a.*.b",
+                want: Some(vec![field("a"), Step::Wildcard, field("b")]),
+            },
+            TestCase {
+                query_str: "$.a[*].id",
+                want: Some(vec![field("a"), Step::Wildcard, field("id")]),
+            },
+            TestCase {
+                query_str: "$.a[*][*]",
+                want: Some(vec![field("a"), Step::Wildcard, Step::Wildcard]),
+            },
+            TestCase {
+                query_str: "$.a*",
+                want: Some(vec![field("a*")]),
+            },
+            TestCase {
+                query_str: "$.a[*",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a[**]",
+                want: None,
+            },
+            TestCase {
+                query_str: "$['user.name']",
+                want: Some(vec![field("user.name")]),
+            },
+            TestCase {
+                query_str: r#"$["a b"]"#,
+                want: Some(vec![field("a b")]),
+            },
+            TestCase {
+                query_str: "$['']",
+                want: Some(vec![field("")]),
+            },
+            TestCase {
+                query_str: "$.a['b.c'][0].d['*']",
+                want: Some(vec![field("a"), field("b.c"), Step::Index(0), field("d"), field("*")]),
+            },
+            TestCase {
+                query_str: r#"$['it\'s']["a\"b"]['a\\b']['"']"#,
+                want: Some(vec![field("it's"), field(r#"a"b"#), field(r#"a\b"#), field(r#"""#)]),
+            },
+            TestCase {
+                query_str: "$['a",
+                want: None,
+            },
+            TestCase {
+                query_str: "$['a'",
+                want: None,
+            },
+            TestCase {
+                query_str: r#"$['a"]"#,
+                want: None,
+            },
+            TestCase {
+                query_str: r#"$['a\n']"#,
+                want: None,
+            },
+            TestCase {
+                query_str: "$['a']b",
+                want: None,
+            },
+            TestCase {
+                query_str: "$[a]",
+                want: None,
+            },
+            TestCase {
+                query_str: "$[*]",
+                want: Some(vec![Step::Wildcard]),
+            },
+            TestCase {
+                query_str: "$[*].a",
+                want: Some(vec![Step::Wildcard, field("a")]),
+            },
+            TestCase {
+                query_str: "$..a",
+                want: Some(vec![Step::Descendant, field("a")]),
+            },
+            TestCase {
+                query_str: "$.a..b.c",
+                want: Some(vec![field("a"), Step::Descendant, field("b"), field("c")]),
+            },
+            TestCase {
+                query_str: "$..*",
+                want: Some(vec![Step::Descendant, Step::Wildcard]),
+            },
+            TestCase {
+                query_str: "$..[0]",
+                want: Some(vec![Step::Descendant, Step::Index(0)]),
+            },
+            TestCase {
+                query_str: "$..['a.b']",
+                want: Some(vec![Step::Descendant, field("a.b")]),
+            },
+            TestCase {
+                query_str: "$..",
+                want: None,
+            },
+            TestCase {
+                query_str: "$.a..",
+                want: None,
+            },
+            TestCase {
+                query_str: "$...a",
+                want: None,
             },
         ];
         for t in test_cases {
-            let got = is_valid_query_str(t.query_str.as_bytes());
+            let got = parse_query_str(t.query_str.as_bytes());
             assert_eq!(t.want, got);
         }
     }
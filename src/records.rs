@@ -0,0 +1,347 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use super::error::Error;
+use super::index_builder::IndexBuilder;
+use super::parser::Collector;
+use super::pikkr::Pikkr;
+use super::result::Result;
+use super::simd::Backend;
+use super::utf8::{CR, HT, LF, SPACE};
+use super::value::Value;
+
+/// A reader which parses each line of a stream as a JSON record
+///
+/// This is not an `Iterator` because a record borrows the buffer of the reader, which is reused for the following records.
+pub struct Records<'p, 'a: 'p, R> {
+    pikkr: &'p mut Pikkr<'a>,
+    reader: R,
+    backend: Backend,
+
+    buf: Vec<u8>,
+    b_lf: Vec<u64>,
+    spans: Vec<Option<Range<usize>>>,
+
+    pos: usize,
+    line: usize,
+    eof: bool,
+}
+
+impl<'p, 'a, R: BufRead> Records<'p, 'a, R> {
+    #[inline]
+    pub(crate) fn new(pikkr: &'p mut Pikkr<'a>, reader: R) -> Self {
+        let spans = vec![None; pikkr.num_queries()];
+        Records {
+            pikkr,
+            reader,
+            backend: Backend::detect(),
+
+            buf: Vec::new(),
+            b_lf: Vec::new(),
+            spans,
+
+            pos: 0,
+            line: 0,
+            eof: false,
+        }
+    }
+
+    /// Reads the next record and parses it, skipping blank lines.
+    ///
+    /// This function will return a `None` at the end of the stream.
+    /// An error has the line number where it occurred, and the following records can still be read unless it is an I/O error.
+    /// After an I/O error, the line which has been partially read is dropped and this function returns a `None`.
+    pub fn next_record(&mut self) -> Option<Result<Record<'_>>> {
+        let (start, end) = loop {
+            let (start, mut end) = match self.next_line() {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            self.line += 1;
+            if end > start && self.buf[end - 1] == CR {
+                end -= 1;
+            }
+            if !self.buf[start..end].iter().all(|&c| c == SPACE || c == HT || c == CR) {
+                break (start, end);
+            }
+        };
+
+        let rec = &self.buf[start..end];
+        Collector::clear(&mut self.spans[..]);
+        Some(match self.pikkr.collect(rec, &mut self.spans[..]) {
+            Ok(()) => Ok(Record {
+                line: self.line,
+                rec,
+                spans: &self.spans,
+                index_builder: self.pikkr.index_builder(),
+            }),
+            Err(e) => Err(e.at_line(self.line)),
+        })
+    }
+
+    /// Returns the range of the next line in the buffer, excluding the line feed.
+    #[inline]
+    fn next_line(&mut self) -> Result<Option<(usize, usize)>> {
+        loop {
+            if let Some(i) = self.find_lf() {
+                let start = self.pos;
+                self.pos = i + 1;
+                return Ok(Some((start, i)));
+            }
+            if self.eof {
+                if self.pos < self.buf.len() {
+                    let start = self.pos;
+                    self.pos = self.buf.len();
+                    return Ok(Some((start, self.pos)));
+                }
+                return Ok(None);
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Returns the position of the first line feed after `pos`, if available.
+    #[inline]
+    fn find_lf(&self) -> Option<usize> {
+        let si = self.pos / 64;
+        for i in si..self.b_lf.len() {
+            let mut m = self.b_lf[i];
+            if i == si {
+                m &= !0u64 << (self.pos % 64);
+            }
+            if m != 0 {
+                return Some(i * 64 + m.trailing_zeros() as usize);
+            }
+        }
+        None
+    }
+
+    /// Drops the lines which have been read from the buffer and appends the next bytes of the stream to it.
+    #[inline]
+    fn fill(&mut self) -> Result<()> {
+        self.buf.drain(..self.pos);
+        self.b_lf.clear();
+        self.pos = 0;
+        // the rest of the buffer has no line feeds, so only the bytes from its last 64-byte block are scanned
+        let si = self.buf.len() / 64 * 64;
+
+        let n = loop {
+            match self.reader.fill_buf() {
+                Ok(bytes) => {
+                    self.buf.extend_from_slice(bytes);
+                    break bytes.len();
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // the rest of the buffer is an unterminated line, which is not a whole record
+                    self.buf.clear();
+                    self.eof = true;
+                    return Err(Error::from(e).at_line(self.line + 1));
+                }
+            }
+        };
+        self.reader.consume(n);
+        if n == 0 {
+            self.eof = true;
+        }
+
+        self.b_lf.resize(si / 64, 0);
+        self.backend.build_character_bitmap(&self.buf[si..], &mut self.b_lf, LF);
+        Ok(())
+    }
+}
+
+/// A record parsed by `Records`
+#[derive(Debug)]
+pub struct Record<'r> {
+    line: usize,
+    rec: &'r [u8],
+    spans: &'r [Option<Range<usize>>],
+    index_builder: &'r IndexBuilder,
+}
+
+impl<'r> Record<'r> {
+    /// Returns the line number of this record, which starts from 1.
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the raw bytes of this record, excluding the line break.
+    #[inline]
+    pub fn as_bytes(&self) -> &'r [u8] {
+        self.rec
+    }
+
+    /// Returns the value picked up by the `i`-th query, if available.
+    ///
+    /// # Panics
+    /// This function will panic if `i` is not less than the number of queries.
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<&'r [u8]> {
+        self.spans[i].clone().map(|r| &self.rec[r])
+    }
+
+    /// Returns the content of the string picked up by the `i`-th query, whose quotes are stripped and escape sequences are decoded.
+    ///
+    /// The structural index of this record tells whether the string has escape sequences, and the content is borrowed from the record unless it has any.
+    /// This function will return a `None` if no value is picked up, or an error if the value is not a string.
+    ///
+    /// # Panics
+    /// This function will panic if `i` is not less than the number of queries.
+    #[inline]
+    pub fn unescape(&self, i: usize) -> Option<Result<Cow<'r, str>>> {
+        self.spans[i].clone().map(|r| {
+            let escaped = self.index_builder.contains_backslash(r.start, r.end);
+            Value::new(&self.rec[r]).decode_str(escaped)
+        })
+    }
+
+    /// Returns the byte ranges of the values in this record for each query.
+    #[inline]
+    pub fn spans(&self) -> &'r [Option<Range<usize>>] {
+        self.spans
+    }
+
+    /// Returns an iterator over the values picked up by each query.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = Option<&'r [u8]>> + 'r {
+        let rec = self.rec;
+        self.spans.iter().map(move |r| r.clone().map(|r| &rec[r]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::error::ErrorKind;
+    use std::io::{BufReader, Cursor, Read};
+
+    #[test]
+    fn test_records() {
+        let queries = vec!["$.a".as_bytes(), "$.b".as_bytes()];
+        let long = format!(r#"{{"pad": "{}", "b": "long"}}"#, "x".repeat(150));
+        let input = format!("{}\n\n{}\r\n  \r\n{}\n{}\n{}", r#"{"a": 1, "b": "x"}"#, r#"{"b": 2}"#, r#"{"a": }}"#, long, r#"{"a": [3]}"#);
+        struct Want<'a> {
+            line: usize,
+            values: Result<Vec<Option<&'a [u8]>>>,
+        }
+        let wants = vec![
+            Want {
+                line: 1,
+                values: Ok(vec![Some("1".as_bytes()), Some(r#""x""#.as_bytes())]),
+            },
+            Want {
+                line: 3,
+                values: Ok(vec![None, Some("2".as_bytes())]),
+            },
+            Want {
+                line: 5,
+                values: Err(Error::from(ErrorKind::InvalidRecord).at_line(5)),
+            },
+            Want {
+                line: 6,
+                values: Ok(vec![None, Some(r#""long""#.as_bytes())]),
+            },
+            Want {
+                line: 7,
+                values: Ok(vec![Some("[3]".as_bytes()), None]),
+            },
+        ];
+        for capacity in vec![1, 7, 64, 8192] {
+            for train_num in vec![1, 1000000000] {
+                let mut p = Pikkr::new(&queries, train_num).unwrap();
+                let mut records = p.records(BufReader::with_capacity(capacity, Cursor::new(input.as_bytes())));
+                for want in &wants {
+                    let got = records.next_record().unwrap().map(|r| {
+                        assert_eq!(want.line, r.line());
+                        r.iter().collect::<Vec<_>>()
+                    });
+                    assert_eq!(want.values, got);
+                    if let Err(ref e) = got {
+                        assert_eq!(Some(want.line), e.line());
+                    }
+                }
+                assert!(records.next_record().is_none());
+                assert!(records.next_record().is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_record_unescape() {
+        let queries = vec!["$.a".as_bytes(), "$.b".as_bytes()];
+        let input = r#"{"a": "x\ty", "b": 1}
+{"a": "plain"}
+"#;
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        let mut records = p.records(Cursor::new(input.as_bytes()));
+        {
+            let r = records.next_record().unwrap().unwrap();
+            assert_eq!(Some(Ok(Cow::Owned(String::from("x\ty")))), r.unescape(0));
+            assert_eq!(Some(Err(Error::from(ErrorKind::TypeMismatch))), r.unescape(1));
+        }
+        let r = records.next_record().unwrap().unwrap();
+        let got = r.unescape(0);
+        assert!(matches!(got, Some(Ok(Cow::Borrowed("plain")))));
+        assert_eq!(None, r.unescape(1));
+    }
+
+    #[test]
+    fn test_records_io_error() {
+        // a reader which is interrupted before each read
+        struct FailingReader(usize, bool);
+        impl Read for FailingReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.1 = !self.1;
+                if self.1 {
+                    return Err(io::Error::from(io::ErrorKind::Interrupted));
+                }
+                if self.0 == 0 {
+                    return Err(io::Error::other("failed"));
+                }
+                self.0 -= 1;
+                buf[..2].copy_from_slice(b"{}");
+                buf[2] = LF;
+                Ok(3)
+            }
+        }
+        let queries = vec!["$.a".as_bytes()];
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        let mut records = p.records(BufReader::new(FailingReader(2, false)));
+        assert_eq!(Ok(1), records.next_record().unwrap().map(|r| r.line()));
+        assert_eq!(Ok(2), records.next_record().unwrap().map(|r| r.line()));
+        let err = records.next_record().unwrap().unwrap_err();
+        assert_eq!(ErrorKind::Io, err.kind());
+        assert_eq!(Some(3), err.line());
+        assert_eq!(Some(io::ErrorKind::Other), err.io_error().map(|e| e.kind()));
+        assert_eq!("I/O error: failed at line 3", err.to_string());
+        assert!(records.next_record().is_none());
+    }
+
+    #[test]
+    fn test_records_io_error_mid_line() {
+        // a reader which fails after returning its chunks
+        struct FailingReader(Vec<&'static [u8]>);
+        impl Read for FailingReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() {
+                    return Err(io::Error::other("failed"));
+                }
+                let chunk = self.0.remove(0);
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            }
+        }
+        let queries = vec!["$.a".as_bytes()];
+        let mut p = Pikkr::new(&queries, 1).unwrap();
+        let mut records = p.records(BufReader::new(FailingReader(vec![b"{\"a\": 1}\n{\"a\"", b": 2}"])));
+        assert_eq!(Ok(vec![Some("1".as_bytes())]), records.next_record().unwrap().map(|r| r.iter().collect::<Vec<_>>()));
+        let err = records.next_record().unwrap().unwrap_err();
+        assert_eq!(ErrorKind::Io, err.kind());
+        assert_eq!(Some(2), err.line());
+        assert!(records.next_record().is_none());
+        assert!(records.next_record().is_none());
+    }
+}
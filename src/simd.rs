@@ -0,0 +1,207 @@
+//! Implementations of the character bitmap builders, one of which is chosen by the features of the CPU at runtime
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use super::avx2;
+use super::emulated;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use super::sse2;
+use super::utf8::{BACKSLASH, COLON, COMMA, LEFT_BRACE, LEFT_BRACKET, QUOTE, RIGHT_BRACE, RIGHT_BRACKET};
+
+/// 64 bytes loaded into the registers of an implementation
+pub trait Block: Sized {
+    /// Loads the 64 bytes from `p`.
+    ///
+    /// # Safety
+    /// This function guarantees the safety only if `p` is valid for reads of 64 bytes and the CPU supports the instructions of the implementation.
+    unsafe fn load(p: *const u8) -> Self;
+
+    /// Returns the bitmap of the bytes equal to `c`, whose lowest bit is the first byte.
+    ///
+    /// # Safety
+    /// This function guarantees the safety only if the CPU supports the instructions of the implementation.
+    unsafe fn eq(&self, c: u8) -> u64;
+
+    /// Returns the bitmap of the bytes equal to either `c1` or `c2`.
+    ///
+    /// # Safety
+    /// This function guarantees the safety only if the CPU supports the instructions of the implementation.
+    #[inline(always)]
+    unsafe fn eq_any(&self, c1: u8, c2: u8) -> u64 {
+        self.eq(c1) | self.eq(c2)
+    }
+}
+
+/// An implementation of the character bitmap builders
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// The portable implementation, which compares 8 bytes at a time in 64-bit words
+    Scalar,
+    /// The implementation with SSE2 instructions, which compares 16 bytes at a time
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Sse2,
+    /// The implementation with AVX2 instructions, which compares 32 bytes at a time
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Avx2,
+}
+
+impl Backend {
+    /// Returns the fastest implementation supported by the CPU.
+    #[inline]
+    pub fn detect() -> Self {
+        Self::supported().pop().unwrap()
+    }
+
+    /// Returns the implementations supported by the CPU, from the slowest one.
+    pub fn supported() -> Vec<Self> {
+        #[allow(unused_mut)]
+        let mut backends = vec![Backend::Scalar];
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if is_x86_feature_detected!("sse2") {
+                backends.push(Backend::Sse2);
+            }
+            if is_x86_feature_detected!("avx2") {
+                backends.push(Backend::Avx2);
+            }
+        }
+        backends
+    }
+
+    /// Builds the bitmaps of structural characters.
+    ///
+    /// Both braces and brackets are marked in `b_left` and `b_right` because each of them opens a new level.
+    /// The caller has to make sure that this implementation is supported by the CPU.
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn build_structural_character_bitmap(self, s: &[u8], b_backslash: &mut Vec<u64>, b_quote: &mut Vec<u64>, b_colon: &mut Vec<u64>, b_comma: &mut Vec<u64>, b_left: &mut Vec<u64>, b_right: &mut Vec<u64>) {
+        match self {
+            Backend::Scalar => unsafe { build_structural_character_bitmap::<emulated::Block>(s, b_backslash, b_quote, b_colon, b_comma, b_left, b_right) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Sse2 => unsafe { sse2_structural_character_bitmap(s, b_backslash, b_quote, b_colon, b_comma, b_left, b_right) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx2 => unsafe { avx2_structural_character_bitmap(s, b_backslash, b_quote, b_colon, b_comma, b_left, b_right) },
+        }
+    }
+
+    /// Builds the bitmap of the character `c` in `s`.
+    ///
+    /// The caller has to make sure that this implementation is supported by the CPU.
+    #[inline]
+    pub(crate) fn build_character_bitmap(self, s: &[u8], b: &mut Vec<u64>, c: u8) {
+        match self {
+            Backend::Scalar => unsafe { build_character_bitmap::<emulated::Block>(s, b, c) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Sse2 => unsafe { sse2_character_bitmap(s, b, c) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx2 => unsafe { avx2_character_bitmap(s, b, c) },
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "sse2")]
+#[allow(clippy::too_many_arguments)]
+unsafe fn sse2_structural_character_bitmap(s: &[u8], b_backslash: &mut Vec<u64>, b_quote: &mut Vec<u64>, b_colon: &mut Vec<u64>, b_comma: &mut Vec<u64>, b_left: &mut Vec<u64>, b_right: &mut Vec<u64>) {
+    build_structural_character_bitmap::<sse2::Block>(s, b_backslash, b_quote, b_colon, b_comma, b_left, b_right)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "sse2")]
+unsafe fn sse2_character_bitmap(s: &[u8], b: &mut Vec<u64>, c: u8) {
+    build_character_bitmap::<sse2::Block>(s, b, c)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+#[allow(clippy::too_many_arguments)]
+unsafe fn avx2_structural_character_bitmap(s: &[u8], b_backslash: &mut Vec<u64>, b_quote: &mut Vec<u64>, b_colon: &mut Vec<u64>, b_comma: &mut Vec<u64>, b_left: &mut Vec<u64>, b_right: &mut Vec<u64>) {
+    build_structural_character_bitmap::<avx2::Block>(s, b_backslash, b_quote, b_colon, b_comma, b_left, b_right)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+unsafe fn avx2_character_bitmap(s: &[u8], b: &mut Vec<u64>, c: u8) {
+    build_character_bitmap::<avx2::Block>(s, b, c)
+}
+
+/// Loads the 64 bytes of `s` from `i`, which are padded with zeros after the end of `s`.
+#[inline(always)]
+unsafe fn load_block<B: Block>(s: &[u8], i: usize) -> B {
+    if i + 64 <= s.len() {
+        B::load(s.as_ptr().add(i))
+    } else {
+        let mut buf = [0u8; 64];
+        buf[..s.len() - i].copy_from_slice(&s[i..]);
+        B::load(buf.as_ptr())
+    }
+}
+
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+unsafe fn build_structural_character_bitmap<B: Block>(s: &[u8], b_backslash: &mut Vec<u64>, b_quote: &mut Vec<u64>, b_colon: &mut Vec<u64>, b_comma: &mut Vec<u64>, b_left: &mut Vec<u64>, b_right: &mut Vec<u64>) {
+    let mut i = 0;
+    while i < s.len() {
+        let b = load_block::<B>(s, i);
+        b_backslash.push(b.eq(BACKSLASH));
+        b_quote.push(b.eq(QUOTE));
+        b_colon.push(b.eq(COLON));
+        b_comma.push(b.eq(COMMA));
+        b_left.push(b.eq_any(LEFT_BRACE, LEFT_BRACKET));
+        b_right.push(b.eq_any(RIGHT_BRACE, RIGHT_BRACKET));
+        i += 64;
+    }
+}
+
+#[inline(always)]
+unsafe fn build_character_bitmap<B: Block>(s: &[u8], b: &mut Vec<u64>, c: u8) {
+    let mut i = 0;
+    while i < s.len() {
+        b.push(load_block::<B>(s, i).eq(c));
+        i += 64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_detect() {
+        let supported = Backend::supported();
+        assert_eq!(Backend::Scalar, supported[0]);
+        assert_eq!(supported.last(), Some(&Backend::detect()));
+    }
+
+    #[test]
+    fn test_build_character_bitmap() {
+        struct TestCase {
+            s: Vec<u8>,
+            want: Vec<u64>,
+        }
+        let test_cases = vec![
+            TestCase {
+                s: vec![],
+                want: vec![],
+            },
+            TestCase {
+                s: b"a\nb".to_vec(),
+                want: vec![0b10],
+            },
+            TestCase {
+                s: [vec![b'\n'; 64], vec![b'a'; 63], vec![b'\n']].concat(),
+                want: vec![!0, 1 << 63],
+            },
+            TestCase {
+                s: [vec![0; 65], vec![b'\n'; 1]].concat(),
+                want: vec![0, 0b10],
+            },
+        ];
+        for backend in Backend::supported() {
+            for t in &test_cases {
+                let mut b = Vec::new();
+                backend.build_character_bitmap(&t.s, &mut b, b'\n');
+                assert_eq!(t.want, b, "{:?}", backend);
+            }
+        }
+    }
+}
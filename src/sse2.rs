@@ -0,0 +1,32 @@
+#[cfg(target_arch = "x86")]
+use std::arch::x86::{__m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{__m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8};
+use super::simd;
+
+/// 64 bytes in four SSE registers
+pub struct Block([__m128i; 4]);
+
+impl simd::Block for Block {
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn load(p: *const u8) -> Self {
+        Block([
+            _mm_loadu_si128(p as *const __m128i),
+            _mm_loadu_si128(p.add(16) as *const __m128i),
+            _mm_loadu_si128(p.add(32) as *const __m128i),
+            _mm_loadu_si128(p.add(48) as *const __m128i),
+        ])
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn eq(&self, c: u8) -> u64 {
+        let m = _mm_set1_epi8(c as i8);
+        let mut b = 0u64;
+        for (i, v) in self.0.iter().enumerate() {
+            b |= u64::from(_mm_movemask_epi8(_mm_cmpeq_epi8(*v, m)) as u16) << (i * 16);
+        }
+        b
+    }
+}
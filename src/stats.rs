@@ -0,0 +1,158 @@
+use super::error::{Error, ErrorKind};
+use super::parser::Candidates;
+use super::result::Result;
+use fnv::FnvHashSet;
+
+const MAGIC: &[u8] = b"PIKKR";
+const VERSION: u8 = 1;
+
+/// Encodes the positions learned for each node of the query tree whose fingerprint is `fingerprint`.
+///
+/// The numbers are encoded in LEB128 after the header, i.e. the magic bytes, version and fingerprint:
+/// the number of trained records, the number of nodes, and for each node the number of positions followed by them and their counts from the most frequent one.
+pub fn encode(fingerprint: u64, trained_num: usize, stats: &[Candidates]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(MAGIC);
+    buf.push(VERSION);
+    buf.extend_from_slice(&fingerprint.to_le_bytes());
+    write_varint(&mut buf, trained_num as u64);
+    write_varint(&mut buf, stats.len() as u64);
+    for s in stats {
+        write_varint(&mut buf, s.len() as u64);
+        for (i, n) in s.iter() {
+            write_varint(&mut buf, i as u64);
+            write_varint(&mut buf, n);
+        }
+    }
+    buf
+}
+
+/// Decodes the number of trained records and the positions learned for each node, which are encoded by `encode`.
+///
+/// This function will return an error if `buf` is malformed or was encoded for another query tree.
+pub fn decode(buf: &[u8], fingerprint: u64, num_nodes: usize) -> Result<(usize, Vec<Candidates>)> {
+    let header_len = MAGIC.len() + 1 + 8;
+    if buf.len() < header_len || &buf[..MAGIC.len()] != MAGIC {
+        return Err(Error::from(ErrorKind::InvalidStats));
+    }
+    if buf[MAGIC.len()] != VERSION {
+        return Err(Error::from(ErrorKind::InvalidStats));
+    }
+    let mut f = [0; 8];
+    f.copy_from_slice(&buf[MAGIC.len() + 1..header_len]);
+    if u64::from_le_bytes(f) != fingerprint {
+        return Err(Error::from(ErrorKind::InvalidStats));
+    }
+
+    let mut i = header_len;
+    let trained_num = read_varint(buf, &mut i)?;
+    if read_varint(buf, &mut i)? != num_nodes {
+        return Err(Error::from(ErrorKind::InvalidStats));
+    }
+    let mut stats = Vec::with_capacity(num_nodes);
+    let mut positions = FnvHashSet::default();
+    for _ in 0..num_nodes {
+        let len = read_varint(buf, &mut i)?;
+        let mut s = Candidates::default();
+        positions.clear();
+        let mut prev = u64::MAX;
+        for _ in 0..len {
+            let p = read_varint(buf, &mut i)?;
+            let n = read_varint(buf, &mut i)? as u64;
+            // the positions are encoded in the order of their ranks
+            if n == 0 || n > prev || !positions.insert(p) {
+                return Err(Error::from(ErrorKind::InvalidStats));
+            }
+            s.push(p, n);
+            prev = n;
+        }
+        stats.push(s);
+    }
+    if i != buf.len() {
+        return Err(Error::from(ErrorKind::InvalidStats));
+    }
+    Ok((trained_num, stats))
+}
+
+#[inline]
+fn write_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+#[inline]
+fn read_varint(buf: &[u8], i: &mut usize) -> Result<usize> {
+    let mut v = 0u64;
+    let mut shift = 0;
+    loop {
+        let b = *buf.get(*i).ok_or_else(|| Error::from(ErrorKind::InvalidStats))?;
+        *i += 1;
+        if shift > 63 || (shift == 63 && b > 1) {
+            return Err(Error::from(ErrorKind::InvalidStats));
+        }
+        v |= u64::from(b & 0x7f) << shift;
+        if b & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    if v > usize::MAX as u64 {
+        return Err(Error::from(ErrorKind::InvalidStats));
+    }
+    Ok(v as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        fn candidates(positions: &[(usize, u64)]) -> Candidates {
+            let mut s = Candidates::default();
+            for &(i, n) in positions {
+                s.add(i, n);
+            }
+            s
+        }
+        let stats = vec![
+            candidates(&[(3, 2), (0, 1)]),
+            Candidates::default(),
+            candidates(&[(300, 5), (127, 1), (128, 1), (usize::MAX, 1)]),
+        ];
+        let buf = encode(42, 5, &stats);
+        assert_eq!(Ok((5, stats.clone())), decode(&buf, 42, 3));
+        assert_eq!(buf, encode(42, 5, &stats));
+
+        let invalid = Err(Error::from(ErrorKind::InvalidStats));
+        assert_eq!(invalid, decode(&buf, 43, 3));
+        assert_eq!(invalid, decode(&buf, 42, 2));
+        assert_eq!(invalid, decode(&buf[..buf.len() - 1], 42, 3));
+        assert_eq!(invalid, decode(&[buf.clone(), vec![0]].concat(), 42, 3));
+        assert_eq!(invalid, decode(b"PIKKR", 42, 3));
+        assert_eq!(invalid, decode(b"", 42, 3));
+        let mut buf = buf;
+        buf[MAGIC.len()] = VERSION + 1;
+        assert_eq!(invalid, decode(&buf, 42, 3));
+
+        let mut buf = b"PIKKR\x01".to_vec();
+        buf.extend_from_slice(&42u64.to_le_bytes());
+        buf.extend_from_slice(&[5, 1, 2, 3, 2, 0, 1]);
+        assert_eq!(Ok((5, vec![candidates(&[(3, 2), (0, 1)])])), decode(&buf, 42, 1));
+        // a duplicate position
+        buf[19] = 3;
+        assert_eq!(invalid, decode(&buf, 42, 1));
+        // positions out of the order of their ranks
+        buf[18] = 1;
+        buf[19] = 0;
+        buf[20] = 2;
+        assert_eq!(invalid, decode(&buf, 42, 1));
+        // a position which was never observed
+        buf[18] = 2;
+        buf[20] = 0;
+        assert_eq!(invalid, decode(&buf, 42, 1));
+    }
+}
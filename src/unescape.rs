@@ -0,0 +1,157 @@
+use super::error::{Error, ErrorKind};
+use super::result::Result;
+use super::utf8::{BACKSLASH, QUOTE, SLASH};
+
+/// Decodes the escape sequences in the content `s` of a JSON string and appends the result to `buf`.
+///
+/// This function will return an error if `s` contains an invalid escape sequence.
+#[inline]
+pub fn unescape(s: &[u8], buf: &mut Vec<u8>) -> Result<()> {
+    let n = s.len();
+    let mut cs = 0;
+    let mut i = 0;
+    while i < n {
+        if s[i] != BACKSLASH {
+            i += 1;
+            continue;
+        }
+        buf.extend_from_slice(&s[cs..i]);
+        let c = *s.get(i + 1).ok_or_else(invalid_record)?;
+        i += 2;
+        match c {
+            QUOTE | BACKSLASH | SLASH => buf.push(c),
+            b'b' => buf.push(0x08),
+            b'f' => buf.push(0x0c),
+            b'n' => buf.push(0x0a),
+            b'r' => buf.push(0x0d),
+            b't' => buf.push(0x09),
+            b'u' => {
+                let mut cp = u32::from(hex4(s, i)?);
+                i += 4;
+                if (0xd800..0xdc00).contains(&cp) {
+                    if s.get(i) != Some(&BACKSLASH) || s.get(i + 1) != Some(&b'u') {
+                        return Err(invalid_record());
+                    }
+                    let low = u32::from(hex4(s, i + 2)?);
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(invalid_record());
+                    }
+                    cp = 0x10000 + ((cp - 0xd800) << 10) + (low - 0xdc00);
+                    i += 6;
+                }
+                let c = ::std::char::from_u32(cp).ok_or_else(invalid_record)?;
+                let mut b = [0; 4];
+                buf.extend_from_slice(c.encode_utf8(&mut b).as_bytes());
+            }
+            _ => return Err(invalid_record()),
+        }
+        cs = i;
+    }
+    buf.extend_from_slice(&s[cs..]);
+    Ok(())
+}
+
+#[inline]
+fn hex4(s: &[u8], i: usize) -> Result<u16> {
+    let h = s.get(i..i + 4).ok_or_else(invalid_record)?;
+    let mut v = 0u16;
+    for &c in h {
+        let d = match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => return Err(invalid_record()),
+        };
+        v = (v << 4) | u16::from(d);
+    }
+    Ok(v)
+}
+
+#[inline]
+fn invalid_record() -> Error {
+    Error::from(ErrorKind::InvalidRecord)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        struct TestCase<'a> {
+            s: &'a [u8],
+            want: Result<&'a str>,
+        }
+        let test_cases = vec![
+            TestCase {
+                s: b"",
+                want: Ok(""),
+            },
+            TestCase {
+                s: b"abc",
+                want: Ok("abc"),
+            },
+            TestCase {
+                s: br#"\"f1\": \\"#,
+                want: Ok(r#""f1": \"#),
+            },
+            TestCase {
+                s: br#"a\/b\b\f\n\r\tc"#,
+                want: Ok("a/b\u{8}\u{c}\n\r\tc"),
+            },
+            TestCase {
+                s: br#"\u0066\u0031"#,
+                want: Ok("f1"),
+            },
+            TestCase {
+                s: br#"caf\u00e9 \u00E9"#,
+                want: Ok("café é"),
+            },
+            TestCase {
+                s: br#"\u65e5\u672c"#,
+                want: Ok("日本"),
+            },
+            TestCase {
+                s: br#"\ud83d\ude00!"#,
+                want: Ok("😀!"),
+            },
+            TestCase {
+                s: "日本\\n".as_bytes(),
+                want: Ok("日本\n"),
+            },
+            TestCase {
+                s: br#"\"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: br#"\x"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: br#"\u00"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: br#"\u00g0"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: br#"\ud83d"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: br#"\ud83dA"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+            TestCase {
+                s: br#"\ude00"#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+            },
+        ];
+        for t in test_cases {
+            let mut buf = Vec::new();
+            let got = unescape(t.s, &mut buf).map(|_| String::from_utf8(buf).unwrap());
+            assert_eq!(t.want.map(String::from), got);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub const APOSTROPHE: u8 = 0x27;
+pub const ASTERISK: u8 = 0x2a;
 pub const BACKSLASH: u8 = 0x5c;
 pub const COLON: u8 = 0x3a;
 pub const COMMA: u8 = 0x2c;
@@ -6,7 +8,10 @@ pub const DOLLAR: u8 = 0x24;
 pub const DOT: u8 = 0x2e;
 pub const HT: u8 = 0x09;
 pub const LEFT_BRACE: u8 = 0x7b;
+pub const LEFT_BRACKET: u8 = 0x5b;
 pub const LF: u8 = 0x0a;
 pub const QUOTE: u8 = 0x22;
 pub const RIGHT_BRACE: u8 = 0x7d;
+pub const RIGHT_BRACKET: u8 = 0x5d;
+pub const SLASH: u8 = 0x2f;
 pub const SPACE: u8 = 0x20;
@@ -0,0 +1,353 @@
+use std::borrow::Cow;
+use std::str;
+use super::error::{Error, ErrorKind};
+use super::result::Result;
+use super::unescape::unescape;
+use super::utf8::{BACKSLASH, QUOTE};
+
+/// A view of a JSON value picked up from a record
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Value<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> Value<'a> {
+    /// Creates a view of the JSON value `raw`.
+    #[inline]
+    pub fn new(raw: &'a [u8]) -> Self {
+        Value { raw }
+    }
+
+    /// Returns the raw bytes of this value.
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// Returns whether this value is `null` or not.
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.raw == b"null"
+    }
+
+    /// Returns this value as a `bool`.
+    ///
+    /// This function will return an error if the value is not `true` or `false`.
+    #[inline]
+    pub fn as_bool(&self) -> Result<bool> {
+        match self.raw {
+            b"true" => Ok(true),
+            b"false" => Ok(false),
+            _ => Err(Error::from(ErrorKind::TypeMismatch)),
+        }
+    }
+
+    /// Returns this value as an `i64`.
+    ///
+    /// This function will return an error if the value is not an integer or out of the range of `i64`.
+    #[inline]
+    pub fn as_i64(&self) -> Result<i64> {
+        self.as_number()?.parse().map_err(|_| Error::from(ErrorKind::TypeMismatch))
+    }
+
+    /// Returns this value as a `u64`.
+    ///
+    /// This function will return an error if the value is not an integer or out of the range of `u64`.
+    #[inline]
+    pub fn as_u64(&self) -> Result<u64> {
+        self.as_number()?.parse().map_err(|_| Error::from(ErrorKind::TypeMismatch))
+    }
+
+    /// Returns this value as an `f64`.
+    ///
+    /// This function will return an error if the value is not a number.
+    #[inline]
+    pub fn as_f64(&self) -> Result<f64> {
+        self.as_number()?.parse().map_err(|_| Error::from(ErrorKind::TypeMismatch))
+    }
+
+    /// Returns the content of this value as a string, whose escape sequences are decoded.
+    ///
+    /// The content is borrowed from the record if it has no escape sequences.
+    /// This function will return an error if the value is not a string.
+    #[inline]
+    pub fn as_str(&self) -> Result<Cow<'a, str>> {
+        self.decode_str(self.raw.contains(&BACKSLASH))
+    }
+
+    /// Returns the content of this value as a string, which is decoded only if `escaped` is `true`.
+    #[inline]
+    pub(crate) fn decode_str(&self, escaped: bool) -> Result<Cow<'a, str>> {
+        let n = self.raw.len();
+        if n < 2 || self.raw[0] != QUOTE || self.raw[n - 1] != QUOTE {
+            return Err(Error::from(ErrorKind::TypeMismatch));
+        }
+        let s = &self.raw[1..n - 1];
+        if !escaped {
+            return str::from_utf8(s)
+                .map(Cow::Borrowed)
+                .map_err(|_| Error::from(ErrorKind::InvalidRecord));
+        }
+        let mut buf = Vec::with_capacity(s.len());
+        unescape(s, &mut buf)?;
+        String::from_utf8(buf)
+            .map(Cow::Owned)
+            .map_err(|_| Error::from(ErrorKind::InvalidRecord))
+    }
+
+    /// Returns this value as a string if it is a number.
+    #[inline]
+    fn as_number(&self) -> Result<&'a str> {
+        match self.raw.first() {
+            Some(&b'-') | Some(&(b'0'..=b'9')) => {}
+            _ => return Err(Error::from(ErrorKind::TypeMismatch)),
+        }
+        if !is_number(self.raw) {
+            return Err(Error::from(ErrorKind::InvalidRecord));
+        }
+        // a number consists of ASCII characters only
+        Ok(str::from_utf8(self.raw).unwrap())
+    }
+}
+
+impl<'a> From<&'a [u8]> for Value<'a> {
+    #[inline]
+    fn from(raw: &'a [u8]) -> Self {
+        Value::new(raw)
+    }
+}
+
+/// Returns whether `s` is a number in the JSON grammar or not.
+#[inline]
+fn is_number(s: &[u8]) -> bool {
+    let n = s.len();
+    let digits = |mut i: usize| {
+        while i < n && s[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut i = if s.first() == Some(&b'-') { 1 } else { 0 };
+    match s.get(i) {
+        Some(&b'0') => i += 1,
+        Some(&(b'1'..=b'9')) => i = digits(i),
+        _ => return false,
+    }
+    if s.get(i) == Some(&b'.') {
+        let e = digits(i + 1);
+        if e == i + 1 {
+            return false;
+        }
+        i = e;
+    }
+    if s.get(i) == Some(&b'e') || s.get(i) == Some(&b'E') {
+        i += 1;
+        if s.get(i) == Some(&b'+') || s.get(i) == Some(&b'-') {
+            i += 1;
+        }
+        let e = digits(i);
+        if e == i {
+            return false;
+        }
+        i = e;
+    }
+    i == n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_scalars() {
+        struct TestCase<'a> {
+            raw: &'a str,
+            is_null: bool,
+            as_bool: Result<bool>,
+            as_i64: Result<i64>,
+            as_u64: Result<u64>,
+            as_f64: Result<f64>,
+        }
+        fn mismatch<T>() -> Result<T> {
+            Err(Error::from(ErrorKind::TypeMismatch))
+        }
+        fn invalid<T>() -> Result<T> {
+            Err(Error::from(ErrorKind::InvalidRecord))
+        }
+        let test_cases = vec![
+            TestCase {
+                raw: "null",
+                is_null: true,
+                as_bool: mismatch(),
+                as_i64: mismatch(),
+                as_u64: mismatch(),
+                as_f64: mismatch(),
+            },
+            TestCase {
+                raw: "true",
+                is_null: false,
+                as_bool: Ok(true),
+                as_i64: mismatch(),
+                as_u64: mismatch(),
+                as_f64: mismatch(),
+            },
+            TestCase {
+                raw: "false",
+                is_null: false,
+                as_bool: Ok(false),
+                as_i64: mismatch(),
+                as_u64: mismatch(),
+                as_f64: mismatch(),
+            },
+            TestCase {
+                raw: "0",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: Ok(0),
+                as_u64: Ok(0),
+                as_f64: Ok(0.0),
+            },
+            TestCase {
+                raw: "-42",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: Ok(-42),
+                as_u64: mismatch(),
+                as_f64: Ok(-42.0),
+            },
+            TestCase {
+                raw: "18446744073709551615",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: mismatch(),
+                as_u64: Ok(18446744073709551615),
+                as_f64: Ok(18446744073709551615.0),
+            },
+            TestCase {
+                raw: "-1.5e3",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: mismatch(),
+                as_u64: mismatch(),
+                as_f64: Ok(-1500.0),
+            },
+            TestCase {
+                raw: "2E-2",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: mismatch(),
+                as_u64: mismatch(),
+                as_f64: Ok(0.02),
+            },
+            TestCase {
+                raw: "01",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: invalid(),
+                as_u64: invalid(),
+                as_f64: invalid(),
+            },
+            TestCase {
+                raw: "1.",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: invalid(),
+                as_u64: invalid(),
+                as_f64: invalid(),
+            },
+            TestCase {
+                raw: "-",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: invalid(),
+                as_u64: invalid(),
+                as_f64: invalid(),
+            },
+            TestCase {
+                raw: r#""1""#,
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: mismatch(),
+                as_u64: mismatch(),
+                as_f64: mismatch(),
+            },
+            TestCase {
+                raw: "[1]",
+                is_null: false,
+                as_bool: mismatch(),
+                as_i64: mismatch(),
+                as_u64: mismatch(),
+                as_f64: mismatch(),
+            },
+        ];
+        for t in test_cases {
+            let v = Value::from(t.raw.as_bytes());
+            assert_eq!(t.is_null, v.is_null());
+            assert_eq!(t.as_bool, v.as_bool());
+            assert_eq!(t.as_i64, v.as_i64());
+            assert_eq!(t.as_u64, v.as_u64());
+            assert_eq!(t.as_f64, v.as_f64());
+        }
+    }
+
+    #[test]
+    fn test_value_as_str() {
+        struct TestCase<'a> {
+            raw: &'a [u8],
+            want: Result<&'a str>,
+            borrowed: bool,
+        }
+        let test_cases = vec![
+            TestCase {
+                raw: br#""""#,
+                want: Ok(""),
+                borrowed: true,
+            },
+            TestCase {
+                raw: "\"日本\"".as_bytes(),
+                want: Ok("日本"),
+                borrowed: true,
+            },
+            TestCase {
+                raw: br#""\"f1\": \\""#,
+                want: Ok(r#""f1": \"#),
+                borrowed: false,
+            },
+            TestCase {
+                raw: br#""\ud83d\ude00""#,
+                want: Ok("😀"),
+                borrowed: false,
+            },
+            TestCase {
+                raw: br#""\x""#,
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+                borrowed: false,
+            },
+            TestCase {
+                raw: b"\"\xff\"",
+                want: Err(Error::from(ErrorKind::InvalidRecord)),
+                borrowed: false,
+            },
+            TestCase {
+                raw: br#"""#,
+                want: Err(Error::from(ErrorKind::TypeMismatch)),
+                borrowed: false,
+            },
+            TestCase {
+                raw: b"null",
+                want: Err(Error::from(ErrorKind::TypeMismatch)),
+                borrowed: false,
+            },
+            TestCase {
+                raw: br#"{"a": "b"}"#,
+                want: Err(Error::from(ErrorKind::TypeMismatch)),
+                borrowed: false,
+            },
+        ];
+        for t in test_cases {
+            let got = Value::new(t.raw).as_str();
+            assert_eq!(t.borrowed, matches!(got, Ok(Cow::Borrowed(_))));
+            assert_eq!(t.want.map(Cow::Borrowed), got);
+        }
+    }
+}
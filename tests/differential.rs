@@ -0,0 +1,164 @@
+extern crate pikkr;
+
+mod differential {
+    use pikkr::Pikkr;
+
+    /// A xorshift generator, which makes the records reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    const KEYS: &[&str] = &["a", "b", "c", "x", r#"\u0061"#];
+    const QUERIES: &[&str] = &["$.a", "$.b.a", "$.b.c", "$.c[0]", "$.c[1].a", "$.x.b.c"];
+    // the queries with wildcards and recursive descents, along with the ones which can be speculated on
+    const PATTERN_QUERIES: &[&str] = &["$.a", "$.b.*", "$.c[*]", "$.c[*].a", "$.c[1].b", "$.x..c", "$.b..[0]", "$.x.b[*]"];
+    const DESCENT_QUERIES: &[&str] = &["$..a", "$..[1]", "$..c.b"];
+
+    fn gen_whitespace(rng: &mut Rng, s: &mut String) {
+        for _ in 0..rng.below(3) {
+            s.push([' ', '\t', '\n'][rng.below(3)]);
+        }
+    }
+
+    /// Generates a value, which is likely to be of the kind `hint` if it is not zero.
+    fn gen_value(rng: &mut Rng, depth: usize, hint: usize, s: &mut String) {
+        gen_whitespace(rng, s);
+        let kind = if depth > 0 && hint > 0 && rng.below(4) > 0 { hint } else { rng.below(if depth == 0 { 4 } else { 7 }) };
+        match kind {
+            0 => s.push_str(&(rng.below(1000) as i64 - 500).to_string()),
+            1 => s.push_str(["true", "null", "-1.5e3"][rng.below(3)]),
+            2 => s.push_str(r#""a: {\"b\", [\\]}""#),
+            3 => s.push_str(r#""c""#),
+            4 | 5 => gen_object(rng, depth - 1, s),
+            _ => {
+                s.push('[');
+                for i in 0..rng.below(4) {
+                    if i > 0 {
+                        s.push(',');
+                    }
+                    gen_value(rng, depth - 1, 4, s);
+                }
+                gen_whitespace(rng, s);
+                s.push(']');
+            }
+        }
+        gen_whitespace(rng, s);
+    }
+
+    /// Generates an object whose fields are mostly in a fixed order, but sometimes duplicated or moved.
+    fn gen_object(rng: &mut Rng, depth: usize, s: &mut String) {
+        let mut keys = KEYS[..4].to_vec();
+        match rng.below(8) {
+            0 => {
+                let k = KEYS[rng.below(KEYS.len())];
+                let i = rng.below(keys.len() + 1);
+                keys.insert(i, k);
+            }
+            1 => {
+                let i = rng.below(keys.len());
+                let j = rng.below(keys.len());
+                keys.swap(i, j);
+            }
+            2 => {
+                let i = rng.below(keys.len());
+                keys.remove(i);
+            }
+            _ => {}
+        }
+        s.push('{');
+        for (i, k) in keys.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            gen_whitespace(rng, s);
+            s.push('"');
+            s.push_str(k);
+            s.push('"');
+            gen_whitespace(rng, s);
+            s.push(':');
+            let hint = match *k {
+                "b" | "x" => 4,
+                "c" => 6,
+                _ => 0,
+            };
+            gen_value(rng, depth, hint, s);
+        }
+        gen_whitespace(rng, s);
+        s.push('}');
+    }
+
+    fn gen_records(seed: u64, n: usize) -> Vec<String> {
+        let mut rng = Rng(seed);
+        (0..n)
+            .map(|_| {
+                let mut s = String::new();
+                gen_object(&mut rng, 3, &mut s);
+                s
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strict_speculative_parse_equals_basic_parse() {
+        for &seed in &[1, 0x9e37_79b9_7f4a_7c15, 0xdead_beef] {
+            let recs = gen_records(seed, 2000);
+            for &online in &[false, true] {
+                let mut basic = Pikkr::new(QUERIES, usize::MAX).unwrap();
+                let mut strict = Pikkr::new(QUERIES, 20).unwrap();
+                strict.set_strict(true);
+                strict.set_online_learning(online);
+                for rec in &recs {
+                    assert_eq!(basic.parse(rec), strict.parse(rec), "{}", rec);
+                }
+                let metrics = strict.metrics();
+                assert!(metrics.speculative_hits > 0);
+                assert!(metrics.fallbacks > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn strict_speculative_parse_equals_basic_parse_for_patterns() {
+        for &seed in &[1, 0x9e37_79b9_7f4a_7c15, 0xdead_beef] {
+            let recs = gen_records(seed, 2000);
+            for &queries in &[PATTERN_QUERIES, DESCENT_QUERIES] {
+                let mut basic = Pikkr::new(queries, usize::MAX).unwrap();
+                let mut strict = Pikkr::new(queries, 20).unwrap();
+                strict.set_strict(true);
+                for rec in &recs {
+                    assert_eq!(basic.parse(rec), strict.parse(rec), "{}", rec);
+                    assert_eq!(basic.parse_multi(rec), strict.parse_multi(rec), "{}", rec);
+                }
+                let metrics = strict.metrics();
+                assert!(metrics.basic_parses > 0);
+                if queries == PATTERN_QUERIES {
+                    assert!(metrics.fallbacks > 0);
+                } else {
+                    // a recursive descent from the root leaves nothing to speculate on
+                    assert_eq!(0, metrics.speculative_hits);
+                    assert_eq!(0, metrics.fallbacks);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn speculative_parse_may_differ_from_basic_parse() {
+        let recs = gen_records(1, 2000);
+        let mut basic = Pikkr::new(QUERIES, usize::MAX).unwrap();
+        let mut loose = Pikkr::new(QUERIES, 20).unwrap();
+        let diff_num = recs.iter().filter(|rec| basic.parse(*rec) != loose.parse(*rec)).count();
+        assert!(diff_num > 0);
+    }
+}